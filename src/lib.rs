@@ -1,308 +1,3437 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
+    braced,
+    ext::IdentExt,
     parenthesized,
     parse::{Parse, ParseStream},
     parse_macro_input, parse_quote,
     punctuated::Punctuated,
-    Expr, GenericArgument, Generics, Ident, ImplItem, ImplItemFn, Item,
-    ItemImpl, Pat, PatType, PathArguments, PathSegment, Receiver, Result, Stmt,
-    Token, Type,
+    token,
+    visit_mut::{self, VisitMut},
+    Attribute, Expr, ExprLit, FieldsNamed, FnArg, GenericArgument,
+    GenericParam, Generics, Ident, ImplItem, ImplItemFn, Item, ItemImpl,
+    ItemTrait, Lifetime, Lit, LitInt, LitStr, Pat, PatType, Path,
+    PathArguments, PathSegment, Receiver, Result, Signature, Stmt, Token,
+    TraitItem, TraitItemFn, Type, TypeReference, Visibility, WherePredicate,
 };
 
-const FIELD_TYPES: [&str; 8] =
-    ["bool", "i8", "i16", "i32", "i64", "f32", "f64", "String"];
+mod kw {
+    syn::custom_keyword!(exceptions);
+    syn::custom_keyword!(procedure);
+    syn::custom_keyword!(record);
+}
+
+const MAX_DEFAULT_ARGS: usize = 8;
+
+const MAX_TUPLE_COLUMNS: usize = 16;
+
+fn is_field_type(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+
+    let mut segments = type_path.path.segments.iter().rev();
+    let Some(segment) = segments.next() else {
+        return false;
+    };
+
+    match segment.ident.to_string().as_str() {
+        "bool" | "i8" | "i16" | "i32" | "i64" | "f32" | "f64" | "String" => {
+            true
+        }
+        "Vec" => match &segment.arguments {
+            PathArguments::AngleBracketed(arguments) => {
+                matches!(
+                    arguments.args.first(),
+                    Some(GenericArgument::Type(Type::Path(arg)))
+                        if arg.path.is_ident("u8")
+                )
+            }
+            _ => false,
+        },
+        #[cfg(feature = "uuid")]
+        "Uuid" => true,
+        #[cfg(feature = "chrono")]
+        "DateTime" | "NaiveDate" | "NaiveTime" | "NaiveDateTime" => true,
+        #[cfg(feature = "time")]
+        "OffsetDateTime" | "PrimitiveDateTime" | "Date" | "Time" => true,
+        #[cfg(feature = "rust_decimal")]
+        "Decimal" => true,
+        #[cfg(feature = "bigdecimal")]
+        "BigDecimal" => true,
+        #[cfg(feature = "json")]
+        "Json" | "JsonValue" => true,
+        #[cfg(feature = "json")]
+        "Value" => segments.next().is_some_and(|s| s.ident == "serde_json"),
+        #[cfg(feature = "ipnetwork")]
+        "IpNetwork" | "IpAddr" | "Ipv4Addr" | "Ipv6Addr" => true,
+        #[cfg(feature = "mac_address")]
+        "MacAddress" => true,
+        #[cfg(feature = "bit-vec")]
+        "BitVec" => true,
+        _ => false,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+    All,
+    Pool,
+    Transaction,
+}
 
 struct SqlFn {
+    attrs: Vec<Attribute>,
+    checked: bool,
+    expect_rows: Option<u64>,
+    key: Option<LitStr>,
+    count: Option<String>,
+    chunk_size: Option<LitInt>,
+    on_error: Vec<ErrorMapping>,
+    target: Target,
+    target_attr: Option<Ident>,
+    scalar: Option<bool>,
+    procedure: bool,
     name: Ident,
+    sql_name: String,
+    explicit_name: bool,
     generics: Generics,
-    args: Punctuated<PatType, Token![,]>,
+    args: Punctuated<SqlArg, Token![,]>,
     output: syn::ReturnType,
+    records: Vec<Record>,
 }
 
-impl Parse for SqlFn {
-    fn parse(input: ParseStream) -> Result<Self> {
-        let name: Ident = input.parse()?;
-        let generics: Generics = input.parse()?;
+struct Record {
+    attrs: Vec<Attribute>,
+    ident: Ident,
+    fields: FieldsNamed,
+}
 
-        let content;
-        let _ = parenthesized!(content in input);
+impl Parse for Record {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let _: kw::record = input.parse()?;
+        let ident: Ident = input.parse()?;
+        let fields: FieldsNamed = input.parse()?;
 
-        let args = content.parse_terminated(PatType::parse, Token![,])?;
+        Ok(Record {
+            attrs,
+            ident,
+            fields,
+        })
+    }
+}
 
-        let output: syn::ReturnType = input.parse()?;
+fn parse_output(
+    input: ParseStream,
+    records: &mut Vec<Record>,
+) -> Result<syn::ReturnType> {
+    if !input.peek(Token![->]) {
+        return Ok(syn::ReturnType::Default);
+    }
 
-        let _: Token![;] = input.parse()?;
+    let arrow: Token![->] = input.parse()?;
+    let mut tokens = TokenStream2::new();
 
-        Ok(SqlFn {
-            name,
-            generics,
-            args,
-            output,
-        })
+    while !input.is_empty() && !input.peek(Token![;]) {
+        if input.peek(Token![#]) || input.peek(kw::record) {
+            let record: Record = input.parse()?;
+            record.ident.to_tokens(&mut tokens);
+            records.push(record);
+        } else {
+            input.parse::<TokenTree>()?.to_tokens(&mut tokens);
+        }
     }
+
+    let ty: Type = syn::parse2(tokens)?;
+
+    Ok(syn::ReturnType::Type(arrow, Box::new(ty)))
 }
 
-struct Database {
-    functions: Vec<SqlFn>,
+struct SqlArg {
+    arg: PatType,
+    ident: Ident,
+    default: Option<String>,
 }
 
-impl Parse for Database {
+impl Parse for SqlArg {
     fn parse(input: ParseStream) -> Result<Self> {
-        let mut functions: Vec<SqlFn> = Vec::new();
+        let mut attrs: Vec<Attribute> = Vec::new();
+        let mut default = false;
+        let mut sql_name: Option<LitStr> = None;
 
-        while !input.is_empty() {
-            functions.push(input.parse()?);
+        for attr in input.call(Attribute::parse_outer)? {
+            if !attr.path().is_ident("sql") {
+                attrs.push(attr);
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("default") {
+                    default = true;
+                    Ok(())
+                } else if meta.path.is_ident("name") {
+                    sql_name = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported `sql` attribute"))
+                }
+            })?;
         }
 
-        Ok(Database { functions })
-    }
-}
+        let mut arg: PatType = input.parse()?;
+        arg.attrs = attrs;
 
-fn extract_generic_arg(segment: &PathSegment) -> &Type {
-    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
-        panic!("Expected angle bracketed arguments");
-    };
+        let ident = match arg.pat.as_ref() {
+            Pat::Ident(pat) if pat.subpat.is_none() => pat.ident.clone(),
+            pat => {
+                return Err(syn::Error::new_spanned(
+                    pat,
+                    "only identifier patterns are supported for arguments",
+                ))
+            }
+        };
 
-    let GenericArgument::Type(ty) = arguments.args.first().unwrap() else {
-        panic!("Expected a type");
-    };
+        if !default {
+            if let Some(name) = sql_name {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`name` is only supported for `default` arguments",
+                ));
+            }
+
+            return Ok(SqlArg {
+                arg,
+                ident,
+                default: None,
+            });
+        }
+
+        let is_option = match arg.ty.as_ref() {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Option"),
+            _ => false,
+        };
+
+        if !is_option {
+            return Err(syn::Error::new_spanned(
+                &arg.ty,
+                "`default` arguments must be of type `Option<T>`",
+            ));
+        }
+
+        let name = match sql_name {
+            Some(name) => name.value(),
+            None => format!("\"{}\"", ident.unraw()),
+        };
+
+        Ok(SqlArg {
+            arg,
+            ident,
+            default: Some(name),
+        })
+    }
+}
 
-    ty
+impl ToTokens for SqlArg {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        self.arg.to_tokens(tokens);
+    }
 }
 
-enum ReturnType<'a> {
-    Default,
-    Field(&'a Type),
-    Row(&'a Type),
-    Rows(&'a Type),
-    Optional(&'a Type),
-    Stream(&'a Type),
+struct ErrorMapping {
+    code: LitStr,
+    error: Expr,
 }
 
-impl<'a> ReturnType<'a> {
-    fn as_type(&self) -> Type {
-        match *self {
-            Self::Default => parse_quote! { ::sqlx::Result<()> },
-            Self::Field(ty) => parse_quote! { ::sqlx::Result<#ty> },
-            Self::Optional(ty) => parse_quote! {
-                ::sqlx::Result<::std::option::Option<#ty>>
-            },
-            Self::Stream(ty) => parse_quote! {
-                ::futures::stream::BoxStream<::sqlx::Result<#ty>>
-            },
-            Self::Rows(ty) => parse_quote! {
-                ::sqlx::Result<::std::vec::Vec<#ty>>
-            },
-            Self::Row(ty) => parse_quote! { ::sqlx::Result<#ty> },
-        }
+fn parse_sqlstate(input: ParseStream) -> Result<LitStr> {
+    let code: LitStr = input.parse()?;
+    let value = code.value();
+
+    if value.len() != 5
+        || !value.bytes().all(|byte| byte.is_ascii_alphanumeric())
+    {
+        return Err(syn::Error::new_spanned(
+            code,
+            "expected a five-character SQLSTATE code",
+        ));
     }
+
+    Ok(code)
 }
 
-impl<'a> From<&'a syn::ReturnType> for ReturnType<'a> {
-    fn from(value: &'a syn::ReturnType) -> Self {
-        match value {
-            syn::ReturnType::Default => Self::Default,
-            syn::ReturnType::Type(_, ty) => match ty.as_ref() {
-                Type::Path(type_path) => {
-                    let segment = type_path.path.segments.first().unwrap();
+impl Parse for ErrorMapping {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let code = input.call(parse_sqlstate)?;
+        let _: Token![=>] = input.parse()?;
+        let error: Expr = input.parse()?;
 
-                    match segment.ident.to_string().as_str() {
-                        "Option" => {
-                            Self::Optional(extract_generic_arg(segment))
-                        }
-                        "Stream" => Self::Stream(extract_generic_arg(segment)),
-                        "Vec" => Self::Rows(extract_generic_arg(segment)),
-                        ident if FIELD_TYPES.contains(&ident) => {
-                            Self::Field(ty)
-                        }
-                        _ => Self::Row(ty),
-                    }
-                }
-                _ => Self::Row(ty),
-            },
-        }
+        Ok(ErrorMapping { code, error })
     }
 }
 
-fn query_for_fn(name: &str, args: usize) -> String {
-    let mut query = format!("SELECT * FROM {name}(");
+impl SqlFn {
+    fn return_type(&self) -> ReturnType<'_> {
+        ReturnType::new(
+            &self.output,
+            self.scalar,
+            self.key.is_some(),
+            self.chunk_size.is_some(),
+        )
+        .expect("return type is validated when parsed")
+    }
 
-    for i in 1..=args {
-        query.push_str(&format!("${i}"));
+    fn in_trait(&self) -> bool {
+        let return_type = self.return_type();
 
-        if i < args {
-            query.push(',');
-        }
+        self.count.is_none()
+            && !return_type.is_cursor()
+            && !return_type.is_owned_stream()
     }
 
-    query.push(')');
+    fn record(&self, ty: &Type) -> Option<&Record> {
+        let Type::Path(type_path) = ty else {
+            return None;
+        };
+
+        let ident = type_path.path.get_ident()?;
 
-    query
+        self.records.iter().find(|record| record.ident == *ident)
+    }
 }
 
-fn make_fn(sql_fn: SqlFn, is_mut: bool, executor: &Expr) -> ImplItemFn {
-    let name = &sql_fn.name;
-    let generics = &sql_fn.generics;
-    let args = &sql_fn.args;
-    let return_type = ReturnType::from(&sql_fn.output);
-    let result = return_type.as_type();
-    let receiver: Receiver = if is_mut {
-        parse_quote! { &mut self }
-    } else {
-        parse_quote! { &self }
-    };
+fn is_borrowed(tokens: TokenStream2) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Punct(punct) => matches!(punct.as_char(), '&' | '\''),
+        TokenTree::Group(group) => is_borrowed(group.stream()),
+        _ => false,
+    })
+}
 
-    let mut function: ImplItemFn = parse_quote! {
-        pub async fn #name #generics(#receiver, #args) -> #result {}
-    };
+fn parse_str_attr(attr: &Attribute) -> Result<LitStr> {
+    let value = &attr.meta.require_name_value()?.value;
 
-    if let ReturnType::Stream(_) = &return_type {
-        function.sig.asyncness = None;
+    match value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Ok(lit.clone()),
+        _ => Err(syn::Error::new_spanned(value, "expected a string literal")),
     }
+}
 
-    let query_string =
-        query_for_fn(&sql_fn.name.to_string(), sql_fn.args.len());
+impl Parse for SqlFn {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs: Vec<Attribute> = Vec::new();
+        let mut checked = false;
+        let mut expect_rows: Option<u64> = None;
+        let mut key: Option<LitStr> = None;
+        let mut count: Option<LitStr> = None;
+        let mut chunk_size: Option<LitInt> = None;
+        let mut on_error: Vec<ErrorMapping> = Vec::new();
+        let mut sql_name: Option<String> = None;
 
-    let query: Ident = {
-        let query = match &return_type {
-            ReturnType::Default => "query",
-            _ => "query_as",
-        };
+        let mut target: Option<Target> = None;
+        let mut target_attr: Option<Ident> = None;
+        let mut scalar: Option<bool> = None;
 
-        Ident::new(query, proc_macro2::Span::call_site())
-    };
+        for attr in input.call(Attribute::parse_outer)? {
+            let name = attr.path().get_ident().map(ToString::to_string);
 
-    let stmts = &mut function.block.stmts;
+            match name.as_deref() {
+                Some("checked") => {
+                    attr.meta.require_path_only()?;
+                    checked = true;
+                }
+                Some("expect_rows") => {
+                    let rows: LitInt = attr.parse_args()?;
+                    expect_rows = Some(rows.base10_parse()?);
+                }
+                Some("key") => key = Some(parse_str_attr(&attr)?),
+                Some("count") => count = Some(parse_str_attr(&attr)?),
+                Some("on_error") => on_error.extend(attr.parse_args_with(
+                    Punctuated::<ErrorMapping, Token![,]>::parse_terminated,
+                )?),
+                Some("chunk_size") => {
+                    let size: LitInt = attr.parse_args()?;
 
-    stmts.push(parse_quote! {
-        let mut query = ::sqlx::#query(#query_string);
-    });
+                    if size.base10_parse::<usize>()? == 0 {
+                        return Err(syn::Error::new_spanned(
+                            size,
+                            "`chunk_size` must be greater than zero",
+                        ));
+                    }
 
-    for arg in args {
-        let var = &match arg.pat.as_ref() {
-            Pat::Ident(ident) => ident,
-            _ => panic!("Only identifier patterns are supported for arguments"),
-        }
-        .ident;
+                    chunk_size = Some(size);
+                }
+                Some(key @ ("scalar" | "row")) => {
+                    attr.meta.require_path_only()?;
 
-        stmts.push(parse_quote! {
-            let mut query = query.bind(#var);
-        });
-    }
+                    if scalar.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            attr,
+                            "`scalar` and `row` are mutually exclusive",
+                        ));
+                    }
 
-    let last: Expr = match return_type {
-        ReturnType::Default => {
-            stmts.push(parse_quote! {
-                query.fetch_one(#executor).await?;
-            });
-            parse_quote! { Ok(()) }
+                    scalar = Some(key == "scalar");
+                }
+                Some(key @ ("pool_only" | "tx_only")) => {
+                    attr.meta.require_path_only()?;
+
+                    let value = if key == "pool_only" {
+                        Target::Pool
+                    } else {
+                        Target::Transaction
+                    };
+
+                    if target.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            attr,
+                            "`pool_only` and `tx_only` are mutually exclusive",
+                        ));
+                    }
+
+                    target = Some(value);
+                    target_attr = attr.path().get_ident().cloned();
+                }
+                Some("sql") => attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("name") {
+                        let name: LitStr = meta.value()?.parse()?;
+                        sql_name = Some(name.value());
+                        Ok(())
+                    } else {
+                        Err(meta.error("unsupported `sql` attribute"))
+                    }
+                })?,
+                _ => attrs.push(attr),
+            }
         }
-        ReturnType::Field(ty) => {
-            stmts.push(parse_quote! {
-                let row: (#ty,) = query.fetch_one(#executor).await?;
-            });
-            parse_quote! { Ok(row.0) }
+
+        let procedure = input.peek(kw::procedure) && input.peek2(Ident);
+        if procedure {
+            let _: kw::procedure = input.parse()?;
         }
-        ReturnType::Row(_) => parse_quote! {
-            query.fetch_one(#executor).await
-        },
-        ReturnType::Rows(_) => parse_quote! {
-            query.fetch_all(#executor).await
-        },
-        ReturnType::Optional(_) => parse_quote! {
-            query.fetch_optional(#executor).await
-        },
-        ReturnType::Stream(_) => parse_quote! {
-            query.fetch(#executor)
-        },
-    };
 
-    stmts.push(Stmt::Expr(last, None));
+        let name: Ident = input.parse()?;
+        let explicit_name = sql_name.is_some();
+        let sql_name = sql_name.unwrap_or_else(|| name.unraw().to_string());
+        let generics: Generics = input.parse()?;
 
-    parse_quote! { #function }
-}
+        let content;
+        let _ = parenthesized!(content in input);
 
-#[proc_macro]
-pub fn database(input: TokenStream) -> TokenStream {
-    let db = parse_macro_input!(input as Database);
+        let args = content.parse_terminated(SqlArg::parse, Token![,])?;
 
-    let decl: Item = Item::Struct(parse_quote! {
-        pub struct Database {
-            pool: ::sqlx::postgres::PgPool,
-        }
-    });
+        let mut records: Vec<Record> = Vec::new();
+        let output = parse_output(input, &mut records)?;
 
-    let mut imp: ItemImpl = parse_quote! {
-        impl Database {
-            pub fn new(pool: ::sqlx::postgres::PgPool) -> Self {
-                Self { pool }
+        let return_type = ReturnType::new(
+            &output,
+            scalar,
+            key.is_some(),
+            chunk_size.is_some(),
+        )?;
+
+        if let Some(Type::Tuple(tuple)) = return_type.row_type() {
+            if tuple.elems.len() > MAX_TUPLE_COLUMNS {
+                return Err(syn::Error::new_spanned(
+                    tuple,
+                    format!(
+                        "tuples with more than {MAX_TUPLE_COLUMNS} columns \
+                        are not supported"
+                    ),
+                ));
             }
 
-            pub async fn close(&self) {
-                self.pool.close().await
+            if checked {
+                return Err(syn::Error::new_spanned(
+                    tuple,
+                    "`checked` functions cannot return tuples",
+                ));
             }
         }
-    };
 
-    let executor: Expr = parse_quote! { &self.pool };
+        if let Some(key) = &key {
+            if !matches!(
+                return_type,
+                ReturnType::KeyedMap(_) | ReturnType::KeysetPage(_)
+            ) {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    "`key` requires a map or `Page` return type",
+                ));
+            }
+        }
 
-    for function in db.functions {
-        imp.items
-            .push(ImplItem::Fn(make_fn(function, false, &executor)));
-    }
+        if checked && return_type.is_map() {
+            return Err(syn::Error::new(
+                name.span(),
+                "`checked` functions cannot return maps",
+            ));
+        }
 
-    let output = quote! {
-        #decl
-        #imp
-    };
+        if return_type.is_page() {
+            if checked {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`checked` functions cannot return pages",
+                ));
+            }
 
-    output.into()
-}
+            if let ReturnType::OffsetPage(Page {
+                cursor: Some(cursor),
+                ..
+            }) = return_type
+            {
+                let is_i64 = matches!(
+                    cursor,
+                    Type::Path(type_path) if type_path.path.is_ident("i64")
+                );
 
-#[proc_macro]
-pub fn transaction(input: TokenStream) -> TokenStream {
-    let tx = parse_macro_input!(input as Database);
+                if !is_i64 {
+                    return Err(syn::Error::new_spanned(
+                        cursor,
+                        "offset pagination requires an `i64` cursor: \
+                        use `#[key = \"...\"]` for keyset pagination",
+                    ));
+                }
+            }
 
-    let decl: Item = Item::Struct(parse_quote! {
-        pub struct Transaction {
-            inner: ::sqlx::Transaction<'static, ::sqlx::postgres::Postgres>,
+            if count.is_some() && args.iter().any(|arg| arg.default.is_some()) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`count` cannot be used with `default` arguments",
+                ));
+            }
+        } else if let Some(count) = &count {
+            return Err(syn::Error::new_spanned(
+                count,
+                "`count` requires a `Page` return type",
+            ));
         }
-    });
 
-    let begin: ItemImpl = parse_quote! {
-        impl Database {
-            pub async fn begin(&self) -> ::sqlx::Result<Transaction> {
-                Ok(Transaction { inner: self.pool.begin().await? })
+        match (&chunk_size, &return_type) {
+            (Some(_), ReturnType::FieldChunks(_, None))
+            | (Some(_), ReturnType::Chunks(_, None))
+            | (None, _) => (),
+            (Some(size), _) => {
+                return Err(syn::Error::new_spanned(
+                    size,
+                    "`chunk_size` requires a `Stream<Vec<T>>` return type",
+                ))
             }
+        }
 
+        if let ReturnType::FieldChunks(_, Some(size))
+        | ReturnType::Chunks(_, Some(size)) = &return_type
+        {
+            if let GenericArgument::Const(Expr::Lit(ExprLit {
+                lit: Lit::Int(size),
+                ..
+            })) = size
+            {
+                if size.base10_parse::<usize>()? == 0 {
+                    return Err(syn::Error::new_spanned(
+                        size,
+                        "chunk size must be greater than zero",
+                    ));
+                }
+            }
         }
-    };
 
-    let mut imp: ItemImpl = parse_quote! {
-        impl Transaction {
-            pub async fn commit(self) -> ::sqlx::Result<()> {
-                self.inner.commit().await
+        if expect_rows.is_some() && !return_type.is_execute() {
+            return Err(syn::Error::new(
+                name.span(),
+                "`expect_rows` requires a return type of `()`, \
+                `RowsAffected`, or `QueryResult`",
+            ));
+        }
+
+        if return_type.is_cursor() {
+            if checked {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`checked` functions cannot return cursors",
+                ));
             }
 
-            pub async fn rollback(self) -> ::sqlx::Result<()> {
-                self.inner.rollback().await
+            if matches!(target, Some(Target::Pool)) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "cursors are only available on transactions",
+                ));
             }
+
+            target = Some(Target::Transaction);
         }
-    };
 
-    let executor: Expr = parse_quote! { &mut *self.inner };
+        if return_type.is_owned_stream() {
+            if matches!(target, Some(Target::Transaction)) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`OwnedStream` is only available on the database",
+                ));
+            }
+
+            if let Some(arg) = args
+                .iter()
+                .find(|arg| is_borrowed(arg.arg.ty.to_token_stream()))
+            {
+                return Err(syn::Error::new_spanned(
+                    &arg.arg.ty,
+                    "`OwnedStream` arguments cannot borrow",
+                ));
+            }
+
+            target = Some(Target::Pool);
+        }
+
+        let mut reserved = vec!["query"];
+
+        if args.iter().any(|arg| arg.default.is_some()) {
+            reserved.push("variant");
+        }
+
+        if count.is_some() {
+            reserved.push("total");
+        }
+
+        if return_type.is_page() {
+            reserved.push("page");
+        }
+
+        if return_type.is_cursor() {
+            reserved.extend(["cursor", "sql"]);
+        }
+
+        let reserved = args
+            .iter()
+            .find(|arg| reserved.iter().any(|name| arg.ident == name));
+
+        if let Some(arg) = reserved {
+            return Err(syn::Error::new_spanned(
+                &arg.ident,
+                format!("`{}` is reserved by the generated method", arg.ident),
+            ));
+        }
+
+        let _: Token![;] = input.parse()?;
+
+        if checked && args.iter().any(|arg| arg.default.is_some()) {
+            return Err(syn::Error::new(
+                name.span(),
+                "`checked` functions cannot have `default` arguments",
+            ));
+        }
+
+        Ok(SqlFn {
+            attrs,
+            checked,
+            expect_rows,
+            key,
+            count: count.map(|count| count.value()),
+            chunk_size,
+            on_error,
+            target: target.unwrap_or(Target::All),
+            target_attr,
+            scalar,
+            procedure,
+            name,
+            sql_name,
+            explicit_name,
+            generics,
+            args,
+            output,
+            records,
+        })
+    }
+}
 
-    for function in tx.functions {
-        imp.items
-            .push(ImplItem::Fn(make_fn(function, true, &executor)))
+#[derive(Clone, Copy)]
+enum Backend {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl Backend {
+    fn default(span: Span) -> Result<Self> {
+        if cfg!(feature = "postgres") {
+            Ok(Self::Postgres)
+        } else {
+            Err(syn::Error::new(
+                span,
+                "no default backend: specify one with `backend = ...;`",
+            ))
+        }
     }
 
-    let output = quote! {
-        #decl
-        #begin
-        #imp
-    };
+    fn pool(self) -> Type {
+        match self {
+            Self::Postgres => parse_quote! { ::sqlx::postgres::PgPool },
+            Self::MySql => parse_quote! { ::sqlx::mysql::MySqlPool },
+            Self::Sqlite => parse_quote! { ::sqlx::sqlite::SqlitePool },
+        }
+    }
+
+    fn database(self) -> Type {
+        match self {
+            Self::Postgres => parse_quote! { ::sqlx::postgres::Postgres },
+            Self::MySql => parse_quote! { ::sqlx::mysql::MySql },
+            Self::Sqlite => parse_quote! { ::sqlx::sqlite::Sqlite },
+        }
+    }
+
+    fn query_result(self) -> Type {
+        match self {
+            Self::Postgres => parse_quote! { ::sqlx::postgres::PgQueryResult },
+            Self::MySql => parse_quote! { ::sqlx::mysql::MySqlQueryResult },
+            Self::Sqlite => parse_quote! { ::sqlx::sqlite::SqliteQueryResult },
+        }
+    }
+
+    fn quote_identifier(self, name: &str) -> String {
+        match self {
+            Self::MySql => format!("`{}`", name.replace('`', "``")),
+            _ => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    fn uses_call(self) -> bool {
+        matches!(self, Self::MySql)
+    }
+
+    fn supports_procedures(self) -> bool {
+        !matches!(self, Self::Sqlite)
+    }
+
+    fn non_null_scalar(self, query: String, call: bool) -> String {
+        match self {
+            Self::Postgres if !call => format!("{query} AS \"value!\""),
+            _ => query,
+        }
+    }
+
+    fn infers_row_nullability(self, call: bool) -> bool {
+        !matches!(self, Self::Postgres) || call
+    }
+
+    fn non_null_columns(
+        self,
+        query: String,
+        fields: &FieldsNamed,
+        call: bool,
+    ) -> String {
+        if self.infers_row_nullability(call) {
+            return query;
+        }
+
+        let columns = fields
+            .named
+            .iter()
+            .filter_map(|field| field.ident.as_ref())
+            .map(|ident| ident.unraw().to_string())
+            .zip(fields.named.iter().map(|field| option_arg(&field.ty)))
+            .map(|(column, option)| match option {
+                Some(_) => format!("\"{column}\""),
+                None => format!("\"{column}\" AS \"{column}!\""),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        query.replacen('*', &columns, 1)
+    }
+
+    fn supports_transaction_options(self) -> bool {
+        matches!(self, Self::Postgres)
+    }
+
+    fn supports_two_phase_commit(self) -> bool {
+        matches!(self, Self::Postgres)
+    }
+
+    fn supports_exceptions(self) -> bool {
+        matches!(self, Self::Postgres)
+    }
+
+    fn supports_cursors(self) -> bool {
+        matches!(self, Self::Postgres)
+    }
+
+    fn supports_rows_affected(self) -> bool {
+        !matches!(self, Self::Postgres)
+    }
+
+    fn supports_named_args(self) -> bool {
+        matches!(self, Self::Postgres)
+    }
+
+    fn query_for_fn(
+        self,
+        name: &str,
+        args: usize,
+        named: &[&str],
+        call: bool,
+    ) -> String {
+        let mut query = if call {
+            format!("CALL {name}(")
+        } else {
+            format!("SELECT * FROM {name}(")
+        };
+
+        for i in 1..=args {
+            match self {
+                Self::Postgres => query.push_str(&format!("${i}")),
+                Self::MySql => query.push('?'),
+                Self::Sqlite => query.push_str(&format!("?{i}")),
+            }
+
+            if i < args {
+                query.push(',');
+            }
+        }
+
+        for (i, name) in named.iter().enumerate() {
+            if args + i > 0 {
+                query.push(',');
+            }
+
+            query.push_str(&format!("{name} => ${}", args + i + 1));
+        }
+
+        query.push(')');
+
+        query
+    }
+}
+
+impl Parse for Backend {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        let (backend, enabled) = match ident.to_string().as_str() {
+            "postgres" => (Self::Postgres, cfg!(feature = "postgres")),
+            "mysql" => (Self::MySql, cfg!(feature = "mysql")),
+            "sqlite" => (Self::Sqlite, cfg!(feature = "sqlite")),
+            _ => {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "expected one of `postgres`, `mysql` or `sqlite`",
+                ))
+            }
+        };
+
+        if !enabled {
+            return Err(syn::Error::new(
+                ident.span(),
+                format!("the `{ident}` feature is not enabled"),
+            ));
+        }
+
+        Ok(backend)
+    }
+}
+
+struct StructDecl {
+    vis: Visibility,
+    ident: Ident,
+}
+
+impl StructDecl {
+    fn or_default(decl: Option<Self>, name: &str) -> Self {
+        decl.unwrap_or_else(|| StructDecl {
+            vis: parse_quote! { pub },
+            ident: Ident::new(name, Span::call_site()),
+        })
+    }
+}
+
+impl Parse for StructDecl {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let vis: Visibility = input.parse()?;
+        let _: Token![struct] = input.parse()?;
+        let ident: Ident = input.parse()?;
+        let _: Token![;] = input.parse()?;
+
+        Ok(StructDecl { vis, ident })
+    }
+}
+
+struct SqlName {
+    value: String,
+    quoted: bool,
+}
+
+impl SqlName {
+    fn to_sql(&self, backend: Backend) -> String {
+        if self.quoted {
+            backend.quote_identifier(&self.value)
+        } else {
+            self.value.clone()
+        }
+    }
+}
+
+fn parse_name(input: ParseStream) -> Result<SqlName> {
+    if input.peek(LitStr) {
+        Ok(SqlName {
+            value: input.parse::<LitStr>()?.value(),
+            quoted: true,
+        })
+    } else {
+        Ok(SqlName {
+            value: input.call(Ident::parse_any)?.to_string(),
+            quoted: false,
+        })
+    }
+}
+
+fn parse_value<T: Parse>(input: ParseStream) -> Result<T> {
+    let _: Token![=] = input.parse()?;
+    input.parse()
+}
+
+fn parse_flag(input: ParseStream) -> Result<Option<Ident>> {
+    if input.peek(Token![=]) {
+        parse_value(input).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn set_once<T>(
+    option: &mut Option<T>,
+    key: &str,
+    span: Span,
+    value: T,
+) -> Result<()> {
+    if option.is_some() {
+        return Err(syn::Error::new(span, format!("duplicate `{key}` option")));
+    }
+
+    *option = Some(value);
+    Ok(())
+}
+
+struct Exception {
+    attrs: Vec<Attribute>,
+    ident: Ident,
+    code: LitStr,
+}
+
+impl Parse for Exception {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let ident: Ident = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let code = input.call(parse_sqlstate)?;
+
+        Ok(Exception { attrs, ident, code })
+    }
+}
+
+struct Exceptions {
+    keyword: kw::exceptions,
+    ident: Ident,
+    variants: Punctuated<Exception, Token![,]>,
+}
+
+impl Parse for Exceptions {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let keyword: kw::exceptions = input.parse()?;
+        let ident = if input.peek(Ident) {
+            input.parse()?
+        } else {
+            Ident::new("DatabaseError", Span::call_site())
+        };
+
+        let content;
+        let _ = braced!(content in input);
+        let variants = content.parse_terminated(Exception::parse, Token![,])?;
+
+        for (i, variant) in variants.iter().enumerate() {
+            if variant.ident == "Sqlx" {
+                return Err(syn::Error::new_spanned(
+                    &variant.ident,
+                    "`Sqlx` is reserved for other database errors",
+                ));
+            }
+
+            for previous in variants.iter().take(i) {
+                if previous.ident == variant.ident {
+                    return Err(syn::Error::new_spanned(
+                        &variant.ident,
+                        "duplicate exception",
+                    ));
+                }
+
+                if previous.code.value() == variant.code.value() {
+                    return Err(syn::Error::new_spanned(
+                        &variant.code,
+                        "duplicate SQLSTATE code",
+                    ));
+                }
+            }
+        }
+
+        Ok(Exceptions {
+            keyword,
+            ident,
+            variants,
+        })
+    }
+}
+
+struct Database {
+    decl: Option<StructDecl>,
+    backend: Backend,
+    database: Option<Path>,
+    error: Option<Path>,
+    exceptions: Option<Exceptions>,
+    rows_error: Option<Ident>,
+    transaction: Option<Option<Ident>>,
+    options: Option<Option<Ident>>,
+    prepared: Option<Option<Ident>>,
+    savepoint: Option<Option<Ident>>,
+    executor: Option<Option<Ident>>,
+    pagination: Option<Option<Ident>>,
+    cursor: Option<Option<Ident>>,
+    derive: Punctuated<Path, Token![,]>,
+    functions: Vec<SqlFn>,
+}
+
+impl Parse for Database {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut decl: Option<StructDecl> = None;
+        let mut backend: Option<Backend> = None;
+        let mut database: Option<Path> = None;
+        let mut error: Option<Path> = None;
+        let mut exceptions: Option<Exceptions> = None;
+        let mut rows_error: Option<Option<Ident>> = None;
+        let mut transaction: Option<Option<Ident>> = None;
+        let mut options: Option<Option<Ident>> = None;
+        let mut prepared: Option<Option<Ident>> = None;
+        let mut savepoint: Option<Option<Ident>> = None;
+        let mut executor: Option<Option<Ident>> = None;
+        let mut pagination: Option<Option<Ident>> = None;
+        let mut cursor: Option<Option<Ident>> = None;
+        let mut derive: Option<Punctuated<Path, Token![,]>> = None;
+        let mut schema: Option<SqlName> = None;
+
+        loop {
+            if input.peek(Token![pub]) || input.peek(Token![struct]) {
+                let span = input.span();
+                let value = input.parse()?;
+                set_once(&mut decl, "struct", span, value)?;
+                continue;
+            }
+
+            if input.peek(kw::exceptions)
+                && (input.peek2(Ident) || input.peek2(token::Brace))
+            {
+                let span = input.span();
+                let value = input.parse()?;
+                set_once(&mut exceptions, "exceptions", span, value)?;
+                continue;
+            }
+
+            if !(input.peek(Ident)
+                && (input.peek2(Token![=]) || input.peek2(Token![;])))
+            {
+                break;
+            }
+
+            let key: Ident = input.parse()?;
+            let span = key.span();
+
+            match key.to_string().as_str() {
+                "backend" => set_once(
+                    &mut backend,
+                    "backend",
+                    span,
+                    parse_value(input)?,
+                )?,
+                "database" => set_once(
+                    &mut database,
+                    "database",
+                    span,
+                    parse_value(input)?,
+                )?,
+                "error" => {
+                    set_once(&mut error, "error", span, parse_value(input)?)?
+                }
+                "rows_error" => set_once(
+                    &mut rows_error,
+                    "rows_error",
+                    span,
+                    parse_flag(input)?,
+                )?,
+                "transaction" => set_once(
+                    &mut transaction,
+                    "transaction",
+                    span,
+                    parse_flag(input)?,
+                )?,
+                "options" => {
+                    set_once(&mut options, "options", span, parse_flag(input)?)?
+                }
+                "prepared" => set_once(
+                    &mut prepared,
+                    "prepared",
+                    span,
+                    parse_flag(input)?,
+                )?,
+                "savepoint" => set_once(
+                    &mut savepoint,
+                    "savepoint",
+                    span,
+                    parse_flag(input)?,
+                )?,
+                "derive" => {
+                    let _: Token![=] = input.parse()?;
+                    let paths =
+                        input.call(Punctuated::parse_separated_nonempty)?;
+                    set_once(&mut derive, "derive", span, paths)?
+                }
+                "executor" => set_once(
+                    &mut executor,
+                    "executor",
+                    span,
+                    parse_flag(input)?,
+                )?,
+                "pagination" => set_once(
+                    &mut pagination,
+                    "pagination",
+                    span,
+                    parse_flag(input)?,
+                )?,
+                "cursor" => {
+                    set_once(&mut cursor, "cursor", span, parse_flag(input)?)?
+                }
+                "schema" => {
+                    let _: Token![=] = input.parse()?;
+                    set_once(&mut schema, "schema", span, parse_name(input)?)?
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        span,
+                        format!("unknown option `{key}`"),
+                    ))
+                }
+            }
+
+            let _: Token![;] = input.parse()?;
+        }
+
+        let backend = match backend {
+            Some(backend) => backend,
+            None => Backend::default(input.span())?,
+        };
+
+        if options.is_some() && !backend.supports_transaction_options() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`options` is not supported by this backend",
+            ));
+        }
+
+        if prepared.is_some() && !backend.supports_two_phase_commit() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`prepared` is not supported by this backend",
+            ));
+        }
+
+        if rows_error.is_some() && !backend.supports_rows_affected() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`rows_error` is not supported by this backend",
+            ));
+        }
+
+        let rows_error = rows_error.map(|name| {
+            name.unwrap_or_else(|| Ident::new("RowsError", Span::call_site()))
+        });
+
+        if cursor.is_some() && !backend.supports_cursors() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "`cursor` is not supported by this backend",
+            ));
+        }
+
+        if let Some(exceptions) = &exceptions {
+            if !backend.supports_exceptions() {
+                return Err(syn::Error::new(
+                    exceptions.keyword.span,
+                    "`exceptions` is not supported by this backend",
+                ));
+            }
+
+            if error.is_some() {
+                return Err(syn::Error::new(
+                    exceptions.keyword.span,
+                    "`exceptions` cannot be combined with `error`",
+                ));
+            }
+
+            error = Some(exceptions.ident.clone().into());
+        }
+
+        let mut functions: Vec<SqlFn> = Vec::new();
+
+        while !input.is_empty() {
+            let mut function: SqlFn = input.parse()?;
+
+            if function.procedure && !backend.supports_procedures() {
+                return Err(syn::Error::new(
+                    function.name.span(),
+                    "procedures are not supported by this backend",
+                ));
+            }
+
+            if let (Some(mapping), None) = (function.on_error.first(), &error) {
+                return Err(syn::Error::new_spanned(
+                    &mapping.code,
+                    "`on_error` requires an `error` type",
+                ));
+            }
+
+            if function.return_type().is_cursor() && !backend.supports_cursors()
+            {
+                return Err(syn::Error::new(
+                    function.name.span(),
+                    "cursors are not supported by this backend",
+                ));
+            }
+
+            let defaults = function
+                .args
+                .iter()
+                .filter(|arg| arg.default.is_some())
+                .count();
+
+            if defaults > 0 && !backend.supports_named_args() {
+                return Err(syn::Error::new(
+                    function.name.span(),
+                    "`default` arguments are not supported by this backend",
+                ));
+            }
+
+            let call = function.procedure || backend.uses_call();
+            let return_type = function.return_type();
+
+            if return_type.is_page() && pagination.is_none() {
+                return Err(syn::Error::new_spanned(
+                    &function.output,
+                    "`Page` requires `pagination`",
+                ));
+            }
+
+            if return_type.is_cursor() && cursor.is_none() {
+                return Err(syn::Error::new_spanned(
+                    &function.output,
+                    "`Cursor` requires `cursor`",
+                ));
+            }
+
+            if (function.expect_rows.is_some()
+                || matches!(
+                    return_type,
+                    ReturnType::RowsAffected | ReturnType::QueryResult
+                ))
+                && !backend.supports_rows_affected()
+            {
+                return Err(syn::Error::new(
+                    function.name.span(),
+                    "affected rows are not reported by this backend",
+                ));
+            }
+
+            if function.expect_rows.is_some() && rows_error.is_none() {
+                return Err(syn::Error::new(
+                    function.name.span(),
+                    "`expect_rows` requires `rows_error`",
+                ));
+            }
+
+            if function.checked && !backend.infers_row_nullability(call) {
+                if let Some(ty) = return_type.row_type() {
+                    if function.record(ty).is_none() {
+                        return Err(syn::Error::new_spanned(
+                            ty,
+                            "`checked` functions returning rows require an \
+                            inline `record` type on this backend",
+                        ));
+                    }
+                }
+            }
+
+            if defaults > MAX_DEFAULT_ARGS {
+                return Err(syn::Error::new(
+                    function.name.span(),
+                    format!(
+                        "at most {MAX_DEFAULT_ARGS} `default` arguments \
+                        are supported"
+                    ),
+                ));
+            }
+
+            if let Some(schema) = &schema {
+                let schema = schema.to_sql(backend);
+
+                if !function.explicit_name {
+                    function.sql_name =
+                        format!("{schema}.{}", function.sql_name);
+                }
+
+                if let Some(count) = &mut function.count {
+                    *count = format!("{schema}.{count}");
+                }
+            }
+
+            functions.push(function);
+        }
+
+        Ok(Database {
+            decl,
+            backend,
+            database,
+            error,
+            exceptions,
+            rows_error,
+            transaction,
+            options,
+            prepared,
+            savepoint,
+            executor,
+            pagination,
+            cursor,
+            derive: derive.unwrap_or_default(),
+            functions,
+        })
+    }
+}
+
+impl Database {
+    fn page_names(&self) -> (Ident, Ident) {
+        let ident = self
+            .pagination
+            .clone()
+            .flatten()
+            .unwrap_or_else(|| Ident::new("Page", Span::call_site()));
+        let request = format_ident!("{}Request", ident);
+
+        (ident, request)
+    }
+
+    fn cursor_name(&self) -> Ident {
+        self.cursor
+            .clone()
+            .flatten()
+            .unwrap_or_else(|| Ident::new("Cursor", Span::call_site()))
+    }
+
+    fn error_type(&self, function: &SqlFn) -> Option<Path> {
+        match (&self.error, &self.rows_error) {
+            (Some(error), _) => Some(error.clone()),
+            (None, Some(rows_error)) if function.expect_rows.is_some() => {
+                Some(rows_error.clone().into())
+            }
+            _ => None,
+        }
+    }
+}
+
+fn extract_generic_arg(segment: &PathSegment) -> Result<&Type> {
+    match type_args(segment)[..] {
+        [ty] => Ok(ty),
+        _ => Err(syn::Error::new_spanned(
+            segment,
+            format!("expected `{}<T>`", segment.ident),
+        )),
+    }
+}
+
+fn option_arg(ty: &Type) -> Option<&Type> {
+    wrapped_arg(ty, "Option")
+}
+
+fn vec_arg(ty: &Type) -> Option<&Type> {
+    wrapped_arg(ty, "Vec")
+}
+
+fn wrapped_arg<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            match arguments.args.first()? {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn type_args(segment: &PathSegment) -> Vec<&Type> {
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return Vec::new();
+    };
+
+    arguments
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect()
+}
+
+struct Map<'a> {
+    ident: &'a Ident,
+    key: &'a Type,
+    value: &'a Type,
+    group: bool,
+}
+
+impl<'a> Map<'a> {
+    fn new(segment: &'a PathSegment) -> Option<Self> {
+        let [key, value] = type_args(segment)[..] else {
+            return None;
+        };
+
+        let group = vec_arg(value).filter(|_| !is_field_type(value));
+
+        Some(Self {
+            ident: &segment.ident,
+            key,
+            value: group.unwrap_or(value),
+            group: group.is_some(),
+        })
+    }
+
+    fn as_type(&self) -> Type {
+        let Self {
+            ident, key, value, ..
+        } = self;
+
+        if self.group {
+            parse_quote! {
+                ::std::collections::#ident<#key, ::std::vec::Vec<#value>>
+            }
+        } else {
+            parse_quote! { ::std::collections::#ident<#key, #value> }
+        }
+    }
+}
+
+struct Page<'a> {
+    item: &'a Type,
+    cursor: Option<&'a Type>,
+    scalar: bool,
+}
+
+impl<'a> Page<'a> {
+    fn new(
+        segment: &'a PathSegment,
+        scalar: impl Fn(&Type) -> bool,
+    ) -> Option<Self> {
+        let (item, cursor) = match type_args(segment)[..] {
+            [item] => (item, None),
+            [item, cursor] => (item, Some(cursor)),
+            _ => return None,
+        };
+
+        Some(Self {
+            item,
+            cursor,
+            scalar: scalar(item),
+        })
+    }
+
+    fn cursor(&self) -> Type {
+        match self.cursor {
+            Some(cursor) => cursor.clone(),
+            None => parse_quote! { i64 },
+        }
+    }
+
+    fn as_type(&self, ident: &Ident) -> Type {
+        let item = self.item;
+        let cursor = self.cursor();
+
+        parse_quote! { #ident<#item, #cursor> }
+    }
+}
+
+enum ReturnType<'a> {
+    Default,
+    RowsAffected,
+    QueryResult,
+    Field(&'a Type),
+    OptionalField(&'a Type),
+    NullableField(&'a Type),
+    Fields(&'a Type),
+    FieldStream(&'a Type),
+    FieldChunks(&'a Type, Option<&'a GenericArgument>),
+    Row(&'a Type),
+    Rows(&'a Type),
+    Optional(&'a Type),
+    Stream(&'a Type),
+    Chunks(&'a Type, Option<&'a GenericArgument>),
+    KeyedMap(Map<'a>),
+    PairMap(Map<'a>),
+    OffsetPage(Page<'a>),
+    KeysetPage(Page<'a>),
+    FieldCursor(&'a Type),
+    Cursor(&'a Type),
+    FieldOwnedStream(&'a Type),
+    OwnedStream(&'a Type),
+}
+
+impl<'a> ReturnType<'a> {
+    fn new(
+        output: &'a syn::ReturnType,
+        scalar: Option<bool>,
+        keyed: bool,
+        chunked: bool,
+    ) -> Result<Self> {
+        let is_field = |ty: &Type| {
+            !matches!(ty, Type::Tuple(_))
+                && scalar.unwrap_or_else(|| is_field_type(ty))
+        };
+        let is_element_field =
+            |ty: &Type| is_field(ty) || option_arg(ty).is_some_and(is_field);
+
+        let return_type = match output {
+            syn::ReturnType::Default => Self::Default,
+            syn::ReturnType::Type(_, ty) => match ty.as_ref() {
+                Type::Tuple(tuple) if tuple.elems.is_empty() => Self::Default,
+                Type::Path(type_path)
+                    if type_path.path.is_ident("RowsAffected")
+                        || type_path.path.is_ident("u64")
+                            && scalar != Some(true) =>
+                {
+                    Self::RowsAffected
+                }
+                Type::Path(type_path)
+                    if type_path.path.is_ident("QueryResult") =>
+                {
+                    Self::QueryResult
+                }
+                Type::Path(type_path) if !is_field_type(ty) => {
+                    let Some(segment) = type_path.path.segments.last() else {
+                        return Err(syn::Error::new_spanned(
+                            type_path,
+                            "expected a type",
+                        ));
+                    };
+
+                    match segment.ident.to_string().as_str() {
+                        "Option" => {
+                            let ty = extract_generic_arg(segment)?;
+
+                            match option_arg(ty) {
+                                Some(inner) if is_field(inner) => {
+                                    Self::NullableField(inner)
+                                }
+                                _ if is_field(ty) => Self::OptionalField(ty),
+                                _ => Self::Optional(ty),
+                            }
+                        }
+                        "Stream" => {
+                            let ty = extract_generic_arg(segment)?;
+
+                            match vec_arg(ty) {
+                                Some(ty) if chunked && is_element_field(ty) => {
+                                    Self::FieldChunks(ty, None)
+                                }
+                                Some(ty) if chunked => Self::Chunks(ty, None),
+                                Some(_) if !is_field(ty) => {
+                                    return Err(syn::Error::new_spanned(
+                                        segment,
+                                        "streams of `Vec` require \
+                                        `#[chunk_size(N)]`",
+                                    ));
+                                }
+                                _ if is_element_field(ty) => {
+                                    Self::FieldStream(ty)
+                                }
+                                _ => Self::Stream(ty),
+                            }
+                        }
+                        "OwnedStream" => {
+                            let ty = extract_generic_arg(segment)?;
+
+                            if is_element_field(ty) {
+                                Self::FieldOwnedStream(ty)
+                            } else {
+                                Self::OwnedStream(ty)
+                            }
+                        }
+                        "Chunks" => {
+                            let arguments: Vec<&GenericArgument> =
+                                match &segment.arguments {
+                                    PathArguments::AngleBracketed(
+                                        arguments,
+                                    ) => arguments.args.iter().collect(),
+                                    _ => Vec::new(),
+                                };
+
+                            let [GenericArgument::Type(ty), size] =
+                                arguments[..]
+                            else {
+                                return Err(syn::Error::new_spanned(
+                                    segment,
+                                    "expected `Chunks<T, N>`",
+                                ));
+                            };
+
+                            if is_element_field(ty) {
+                                Self::FieldChunks(ty, Some(size))
+                            } else {
+                                Self::Chunks(ty, Some(size))
+                            }
+                        }
+                        "Vec" => {
+                            let ty = extract_generic_arg(segment)?;
+
+                            if is_element_field(ty) {
+                                Self::Fields(ty)
+                            } else {
+                                Self::Rows(ty)
+                            }
+                        }
+                        "HashMap" | "BTreeMap" => match Map::new(segment) {
+                            Some(map) if keyed => Self::KeyedMap(map),
+                            Some(map) if is_element_field(map.value) => {
+                                Self::PairMap(map)
+                            }
+                            Some(_) => {
+                                return Err(syn::Error::new_spanned(
+                                    ty,
+                                    "maps of rows require `#[key = \"...\"]`",
+                                ));
+                            }
+                            None => Self::Row(ty),
+                        },
+                        "Cursor" => match type_args(segment)[..] {
+                            [ty] if is_element_field(ty) => {
+                                Self::FieldCursor(ty)
+                            }
+                            [ty] => Self::Cursor(ty),
+                            _ => Self::Row(ty),
+                        },
+                        "Page" => match Page::new(segment, is_field) {
+                            Some(page) if keyed => Self::KeysetPage(page),
+                            Some(page) => Self::OffsetPage(page),
+                            None => Self::Row(ty),
+                        },
+                        _ if is_field(ty) => Self::Field(ty),
+                        _ => Self::Row(ty),
+                    }
+                }
+                _ if is_field(ty) => Self::Field(ty),
+                _ => Self::Row(ty),
+            },
+        };
+
+        Ok(return_type)
+    }
+
+    fn output(&self, db: &Database, lifetime: &Lifetime) -> Type {
+        match *self {
+            Self::Default => parse_quote! { () },
+            Self::RowsAffected => parse_quote! { u64 },
+            Self::QueryResult => db.backend.query_result(),
+            Self::Field(ty)
+            | Self::Row(ty)
+            | Self::FieldStream(ty)
+            | Self::Stream(ty)
+            | Self::FieldOwnedStream(ty)
+            | Self::OwnedStream(ty) => ty.clone(),
+            Self::OptionalField(ty) | Self::Optional(ty) => parse_quote! {
+                ::std::option::Option<#ty>
+            },
+            Self::NullableField(ty) => parse_quote! {
+                ::std::option::Option<::std::option::Option<#ty>>
+            },
+            Self::Fields(ty)
+            | Self::Rows(ty)
+            | Self::FieldChunks(ty, _)
+            | Self::Chunks(ty, _) => parse_quote! {
+                ::std::vec::Vec<#ty>
+            },
+            Self::KeyedMap(ref map) | Self::PairMap(ref map) => map.as_type(),
+            Self::OffsetPage(ref page) | Self::KeysetPage(ref page) => {
+                page.as_type(&db.page_names().0)
+            }
+            Self::FieldCursor(ty) | Self::Cursor(ty) => {
+                let ident = db.cursor_name();
+                parse_quote! { #ident<#lifetime, #ty> }
+            }
+        }
+    }
+
+    fn as_type(
+        &self,
+        db: &Database,
+        lifetime: &Lifetime,
+        error: Option<&Path>,
+    ) -> Type {
+        let output = self.output(db, lifetime);
+        let result: Type = match error {
+            Some(error) => parse_quote! {
+                ::std::result::Result<#output, #error>
+            },
+            None => parse_quote! { ::sqlx::Result<#output> },
+        };
+
+        if self.is_owned_stream() {
+            parse_quote! { ::futures::stream::BoxStream<'static, #result> }
+        } else if self.is_stream() {
+            parse_quote! { ::futures::stream::BoxStream<#lifetime, #result> }
+        } else {
+            result
+        }
+    }
+
+    fn row_type(&self) -> Option<&'a Type> {
+        match *self {
+            Self::Row(ty)
+            | Self::Rows(ty)
+            | Self::Optional(ty)
+            | Self::Stream(ty)
+            | Self::Chunks(ty, _)
+            | Self::Cursor(ty)
+            | Self::OwnedStream(ty) => Some(ty),
+            Self::KeyedMap(ref map) => Some(map.value),
+            Self::OffsetPage(ref page) | Self::KeysetPage(ref page)
+                if !page.scalar =>
+            {
+                Some(page.item)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_execute(&self) -> bool {
+        matches!(self, Self::Default | Self::RowsAffected | Self::QueryResult)
+    }
+
+    fn is_map(&self) -> bool {
+        matches!(self, Self::KeyedMap(_) | Self::PairMap(_))
+    }
+
+    fn page(&self) -> Option<&Page<'a>> {
+        match self {
+            Self::OffsetPage(page) | Self::KeysetPage(page) => Some(page),
+            _ => None,
+        }
+    }
+
+    fn is_page(&self) -> bool {
+        self.page().is_some()
+    }
+
+    fn is_cursor(&self) -> bool {
+        matches!(self, Self::FieldCursor(_) | Self::Cursor(_))
+    }
+
+    fn is_owned_stream(&self) -> bool {
+        matches!(self, Self::FieldOwnedStream(_) | Self::OwnedStream(_))
+    }
+
+    fn is_stream(&self) -> bool {
+        self.is_owned_stream()
+            || matches!(
+                self,
+                Self::FieldStream(_)
+                    | Self::Stream(_)
+                    | Self::FieldChunks(..)
+                    | Self::Chunks(..)
+            )
+    }
+
+    fn query(&self) -> &'static str {
+        match self {
+            Self::Default
+            | Self::RowsAffected
+            | Self::QueryResult
+            | Self::KeyedMap(_)
+            | Self::KeysetPage(_)
+            | Self::FieldCursor(_)
+            | Self::Cursor(_) => "query",
+            Self::OffsetPage(Page { scalar: true, .. }) => "query_scalar",
+            Self::Field(_)
+            | Self::OptionalField(_)
+            | Self::NullableField(_)
+            | Self::Fields(_)
+            | Self::FieldStream(_)
+            | Self::FieldChunks(..)
+            | Self::FieldOwnedStream(_) => "query_scalar",
+            Self::Row(_)
+            | Self::Rows(_)
+            | Self::Optional(_)
+            | Self::Stream(_)
+            | Self::Chunks(..)
+            | Self::OwnedStream(_)
+            | Self::PairMap(_)
+            | Self::OffsetPage(_) => "query_as",
+        }
+    }
+}
+
+fn make_trait(db: &Database, vis: &Visibility, ident: &Ident) -> ItemTrait {
+    let database = db.backend.database();
+    let receiver: Receiver = parse_quote! { self };
+    let executor: Expr = parse_quote! { self };
+    let lifetime: Lifetime = parse_quote! { 'e };
+
+    let mut item: ItemTrait = parse_quote! {
+        #vis trait #ident<'e>:
+            ::sqlx::Executor<'e, Database = #database> + Sized
+        {
+        }
+    };
+
+    for function in db.functions.iter().filter(|function| function.in_trait()) {
+        let ImplItemFn {
+            attrs,
+            mut sig,
+            mut block,
+            ..
+        } = make_fn(function, db, &receiver, &executor, &lifetime);
+
+        if sig.asyncness.take().is_some() {
+            if let syn::ReturnType::Type(_, output) = &sig.output {
+                sig.output = parse_quote! {
+                    -> impl ::std::future::Future<Output = #output> + Send
+                };
+            }
+
+            block = parse_quote! {{ async move #block }};
+        } else {
+            bind_lifetimes(&mut sig, &lifetime);
+            sig.generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote! { Self: 'e });
+        }
+
+        item.items.push(TraitItem::Fn(TraitItemFn {
+            attrs,
+            sig,
+            default: Some(block),
+            semi_token: None,
+        }));
+    }
+
+    item
+}
+
+struct ElidedLifetimes<'a>(&'a Lifetime);
+
+impl<'a> VisitMut for ElidedLifetimes<'a> {
+    fn visit_type_reference_mut(&mut self, reference: &mut TypeReference) {
+        if reference.lifetime.is_none() {
+            reference.lifetime = Some(self.0.clone());
+        }
+
+        visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.0.clone();
+        }
+    }
+}
+
+fn bind_lifetimes(sig: &mut Signature, lifetime: &Lifetime) {
+    for input in sig.inputs.iter_mut() {
+        if let FnArg::Typed(arg) = input {
+            ElidedLifetimes(lifetime).visit_type_mut(&mut arg.ty);
+        }
+    }
+
+    let predicates: Vec<WherePredicate> = sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Lifetime(param) => {
+                let param = &param.lifetime;
+                Some(parse_quote! { #param: #lifetime })
+            }
+            GenericParam::Type(param) => {
+                let param = &param.ident;
+                Some(parse_quote! { #param: #lifetime })
+            }
+            GenericParam::Const(_) => None,
+        })
+        .collect();
+
+    sig.generics
+        .make_where_clause()
+        .predicates
+        .extend(predicates);
+}
+
+fn make_fn(
+    sql_fn: &SqlFn,
+    db: &Database,
+    receiver: &Receiver,
+    executor: &Expr,
+    lifetime: &Lifetime,
+) -> ImplItemFn {
+    let name = &sql_fn.name;
+    let generics = &sql_fn.generics;
+    let args = &sql_fn.args;
+    let return_type = sql_fn.return_type();
+    let backend = db.backend;
+    let error = db.error_type(sql_fn);
+    let result = return_type.as_type(db, lifetime, error.as_ref());
+
+    let mut function: ImplItemFn = parse_quote! {
+        pub async fn #name #generics(#receiver, #args) -> #result {}
+    };
+
+    function.attrs = sql_fn.attrs.clone();
+
+    if return_type.is_stream() {
+        function.sig.asyncness = None;
+    }
+
+    if let Some(page) = return_type.page() {
+        let cursor = page.cursor();
+        let (_, request) = db.page_names();
+
+        function
+            .sig
+            .inputs
+            .push(parse_quote! { page: #request<#cursor> });
+    }
+
+    let call = sql_fn.procedure || backend.uses_call();
+    let (positional, defaults): (Vec<_>, Vec<_>) =
+        args.iter().partition(|arg| arg.default.is_none());
+    let params = if return_type.is_page() {
+        positional.len() + 2
+    } else {
+        positional.len()
+    };
+
+    let query = Ident::new(return_type.query(), Span::call_site());
+
+    let stmts = &mut function.block.stmts;
+
+    if let Some(count) = &sql_fn.count {
+        let query_string =
+            backend.query_for_fn(count, positional.len(), &[], call);
+        let vars = positional.iter().map(|arg| &arg.ident);
+
+        stmts.push(parse_quote! {
+            let total: i64 = ::sqlx::query_scalar(#query_string)
+                #(.bind(&#vars))*
+                .fetch_one(#executor)
+                .await?;
+        });
+    }
+
+    if sql_fn.checked {
+        let query_string =
+            backend.query_for_fn(&sql_fn.sql_name, positional.len(), &[], call);
+        let vars = positional.iter().map(|arg| &arg.ident);
+
+        let query: Expr = match &return_type {
+            ReturnType::Default
+            | ReturnType::RowsAffected
+            | ReturnType::QueryResult => parse_quote! {
+                ::sqlx::query!(#query_string #(, #vars)*)
+            },
+            ReturnType::Field(ty)
+            | ReturnType::Fields(ty)
+            | ReturnType::FieldStream(ty)
+            | ReturnType::FieldChunks(ty, _)
+            | ReturnType::FieldOwnedStream(ty) => {
+                let query_string = match option_arg(ty) {
+                    Some(_) => query_string,
+                    None => backend.non_null_scalar(query_string, call),
+                };
+
+                parse_quote! {
+                    ::sqlx::query_scalar!(#query_string #(, #vars)*)
+                }
+            }
+            ReturnType::OptionalField(_) | ReturnType::NullableField(_) => {
+                parse_quote! {
+                    ::sqlx::query_scalar!(#query_string #(, #vars)*)
+                }
+            }
+            ReturnType::Row(ty)
+            | ReturnType::Rows(ty)
+            | ReturnType::Optional(ty)
+            | ReturnType::Stream(ty)
+            | ReturnType::Chunks(ty, _)
+            | ReturnType::OwnedStream(ty) => {
+                let query_string = match sql_fn.record(ty) {
+                    Some(record) => backend.non_null_columns(
+                        query_string,
+                        &record.fields,
+                        call,
+                    ),
+                    None => query_string,
+                };
+
+                parse_quote! {
+                    ::sqlx::query_as!(#ty, #query_string #(, #vars)*)
+                }
+            }
+            ReturnType::KeyedMap(_)
+            | ReturnType::PairMap(_)
+            | ReturnType::OffsetPage(_)
+            | ReturnType::KeysetPage(_)
+            | ReturnType::FieldCursor(_)
+            | ReturnType::Cursor(_) => unreachable!(
+                "`checked` functions cannot return maps, pages, or cursors"
+            ),
+        };
+
+        stmts.push(parse_quote! {
+            let query = #query;
+        });
+    } else {
+        let sql: Expr = if defaults.is_empty() {
+            let query_string =
+                backend.query_for_fn(&sql_fn.sql_name, params, &[], call);
+
+            parse_quote! { #query_string }
+        } else {
+            let variants = (0..1usize << defaults.len()).map(|variant| {
+                let named: Vec<&str> = defaults
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| variant & (1 << i) != 0)
+                    .filter_map(|(_, arg)| arg.default.as_deref())
+                    .collect();
+
+                backend.query_for_fn(&sql_fn.sql_name, params, &named, call)
+            });
+            let count = 1usize << defaults.len();
+            let flags = defaults.iter().enumerate().map(|(i, arg)| {
+                let var = &arg.ident;
+                quote! { usize::from(#var.is_some()) << #i }
+            });
+
+            stmts.push(parse_quote! {
+                const QUERIES: [&str; #count] = [#(#variants),*];
+            });
+            stmts.push(parse_quote! {
+                let variant = #(#flags)|*;
+            });
+
+            parse_quote! { QUERIES[variant] }
+        };
+
+        if return_type.is_cursor() {
+            let prefix = name.unraw().to_string();
+
+            stmts.push(parse_quote! {
+                static CURSORS: ::std::sync::atomic::AtomicUsize =
+                    ::std::sync::atomic::AtomicUsize::new(0);
+            });
+            stmts.push(parse_quote! {
+                let cursor = format!(
+                    "\"{}_{}\"",
+                    #prefix,
+                    CURSORS.fetch_add(
+                        1,
+                        ::std::sync::atomic::Ordering::Relaxed,
+                    ),
+                );
+            });
+            stmts.push(parse_quote! {
+                let sql =
+                    format!("DECLARE {} SCROLL CURSOR FOR {}", cursor, #sql);
+            });
+            stmts.push(parse_quote! {
+                let mut query = ::sqlx::query(&sql).persistent(false);
+            });
+        } else {
+            stmts.push(parse_quote! {
+                let mut query = ::sqlx::#query(#sql);
+            });
+        }
+
+        for arg in positional {
+            let var = &arg.ident;
+
+            stmts.push(parse_quote! {
+                let mut query = query.bind(#var);
+            });
+        }
+
+        match return_type {
+            ReturnType::OffsetPage(_) => {
+                stmts.push(parse_quote! {
+                    let offset = page.cursor.unwrap_or(0);
+                });
+                stmts.push(parse_quote! {
+                    let mut query = query.bind(page.limit).bind(offset);
+                });
+            }
+            ReturnType::KeysetPage(_) => stmts.push(parse_quote! {
+                let mut query = query.bind(page.limit).bind(page.cursor);
+            }),
+            _ => (),
+        }
+
+        for arg in defaults {
+            let var = &arg.ident;
+
+            stmts.push(parse_quote! {
+                let mut query = match #var {
+                    Some(value) => query.bind(value),
+                    None => query,
+                };
+            });
+        }
+    }
+
+    let last: Expr = match return_type {
+        ReturnType::Default
+        | ReturnType::RowsAffected
+        | ReturnType::QueryResult
+            if sql_fn.expect_rows.is_some() =>
+        {
+            parse_quote! { query.execute(#executor).await }
+        }
+        ReturnType::RowsAffected => parse_quote! {
+            query
+                .execute(#executor)
+                .await
+                .map(|result| result.rows_affected())
+        },
+        ReturnType::QueryResult => parse_quote! {
+            query.execute(#executor).await
+        },
+        ReturnType::Default if call => {
+            stmts.push(parse_quote! {
+                query.execute(#executor).await?;
+            });
+            parse_quote! { Ok(()) }
+        }
+        ReturnType::Default => {
+            stmts.push(parse_quote! {
+                query.fetch_one(#executor).await?;
+            });
+            parse_quote! { Ok(()) }
+        }
+        ReturnType::Field(_) | ReturnType::Row(_) => parse_quote! {
+            query.fetch_one(#executor).await
+        },
+        ReturnType::OptionalField(_) => parse_quote! {
+            query
+                .fetch_optional(#executor)
+                .await
+                .map(::std::option::Option::flatten)
+        },
+        ReturnType::Fields(_) | ReturnType::Rows(_) => parse_quote! {
+            query.fetch_all(#executor).await
+        },
+        ReturnType::NullableField(_) | ReturnType::Optional(_) => {
+            parse_quote! {
+                query.fetch_optional(#executor).await
+            }
+        }
+        ReturnType::FieldStream(_) | ReturnType::Stream(_) => parse_quote! {
+            query.fetch(#executor)
+        },
+        ReturnType::FieldChunks(_, size) | ReturnType::Chunks(_, size) => {
+            let size = match size {
+                Some(size) => size.to_token_stream(),
+                None => sql_fn.chunk_size.to_token_stream(),
+            };
+
+            stmts.push(parse_quote! {
+                let chunks = ::futures::TryStreamExt::try_chunks(
+                    query.fetch(#executor),
+                    #size,
+                );
+            });
+
+            parse_quote! {
+                ::futures::StreamExt::boxed(::futures::TryStreamExt::map_err(
+                    chunks,
+                    |error| error.1,
+                ))
+            }
+        }
+        ReturnType::KeyedMap(ref map) | ReturnType::PairMap(ref map) => {
+            let ty = map.as_type();
+            let key = map.key;
+            let value = map.value;
+
+            let (pattern, decode): (Pat, Vec<Stmt>) = match &sql_fn.key {
+                Some(column) => (
+                    parse_quote! { row },
+                    parse_quote! {
+                        let key: #key = ::sqlx::Row::try_get(&row, #column)?;
+                        let value: #value = ::sqlx::FromRow::from_row(&row)?;
+                    },
+                ),
+                None => (parse_quote! { (key, value) }, Vec::new()),
+            };
+
+            let insert: Stmt = if map.group {
+                parse_quote! {
+                    map.entry(key)
+                        .or_insert_with(::std::vec::Vec::new)
+                        .push(value);
+                }
+            } else {
+                parse_quote! { map.insert(key, value); }
+            };
+
+            stmts.push(parse_quote! {
+                let mut rows = query.fetch(#executor);
+            });
+            stmts.push(parse_quote! {
+                let mut map: #ty = ::std::default::Default::default();
+            });
+            stmts.push(parse_quote! {
+                while let Some(#pattern) =
+                    ::futures::TryStreamExt::try_next(&mut rows).await?
+                {
+                    #(#decode)*
+                    #insert
+                }
+            });
+
+            parse_quote! { Ok(map) }
+        }
+        ReturnType::OffsetPage(ref page) | ReturnType::KeysetPage(ref page) => {
+            let item = page.item;
+            let total: Expr = if sql_fn.count.is_some() {
+                parse_quote! { Some(total) }
+            } else {
+                parse_quote! { None }
+            };
+
+            if let Some(column) = &sql_fn.key {
+                let decode: Expr = if page.scalar {
+                    parse_quote! { ::sqlx::Row::try_get(row, 0usize) }
+                } else {
+                    parse_quote! {
+                        <#item as ::sqlx::FromRow<'_, _>>::from_row(row)
+                    }
+                };
+
+                stmts.push(parse_quote! {
+                    let rows = query.fetch_all(#executor).await?;
+                });
+                stmts.push(parse_quote! {
+                    let items = rows
+                        .iter()
+                        .map(|row| #decode)
+                        .collect::<::sqlx::Result<::std::vec::Vec<#item>>>()?;
+                });
+                stmts.push(parse_quote! {
+                    let next = match rows.last() {
+                        Some(row)
+                            if page.limit > 0
+                                && i64::try_from(rows.len())
+                                    == Ok(page.limit) =>
+                        {
+                            Some(::sqlx::Row::try_get(row, #column)?)
+                        }
+                        _ => None,
+                    };
+                });
+            } else {
+                stmts.push(parse_quote! {
+                    let items: ::std::vec::Vec<#item> =
+                        query.fetch_all(#executor).await?;
+                });
+                stmts.push(parse_quote! {
+                    let next = (page.limit > 0
+                        && i64::try_from(items.len()) == Ok(page.limit))
+                    .then(|| offset + page.limit);
+                });
+            }
+
+            let (ident, _) = db.page_names();
+
+            parse_quote! {
+                Ok(#ident {
+                    items,
+                    next,
+                    total: #total,
+                })
+            }
+        }
+        ReturnType::FieldOwnedStream(_) | ReturnType::OwnedStream(_) => {
+            stmts.push(parse_quote! {
+                let pool = ::std::clone::Clone::clone(#executor);
+            });
+            stmts.push(parse_quote! {
+                let (mut sender, receiver) =
+                    ::futures::channel::mpsc::channel(1);
+            });
+            stmts.push(parse_quote! {
+                let producer = async move {
+                    let mut rows = query.fetch(&pool);
+
+                    while let Some(row) =
+                        ::futures::StreamExt::next(&mut rows).await
+                    {
+                        if ::futures::SinkExt::send(&mut sender, row)
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                };
+            });
+
+            parse_quote! {
+                ::futures::StreamExt::boxed(::futures::stream::select(
+                    ::futures::StreamExt::filter_map(
+                        ::futures::FutureExt::into_stream(producer),
+                        |()| ::futures::future::ready(None),
+                    ),
+                    receiver,
+                ))
+            }
+        }
+        ReturnType::FieldCursor(ty) | ReturnType::Cursor(ty) => {
+            let decode: Expr = match return_type {
+                ReturnType::FieldCursor(_) => parse_quote! {
+                    ::sqlx::Row::try_get(row, 0usize)
+                },
+                _ => parse_quote! {
+                    <#ty as ::sqlx::FromRow<'_, _>>::from_row(row)
+                },
+            };
+
+            stmts.push(parse_quote! {
+                query.execute(#executor).await?;
+            });
+
+            let ident = db.cursor_name();
+
+            parse_quote! {
+                Ok(#ident {
+                    conn: #executor,
+                    name: cursor,
+                    decode: |row| #decode,
+                })
+            }
+        }
+    };
+
+    let Some(error) = &error else {
+        stmts.push(Stmt::Expr(last, None));
+        return parse_quote! { #function };
+    };
+
+    let codes = sql_fn.on_error.iter().map(|mapping| &mapping.code);
+    let errors = sql_fn.on_error.iter().map(|mapping| &mapping.error);
+    let map_err: Expr = parse_quote! {
+        |error| match error {
+            #(
+                ::sqlx::Error::Database(ref database)
+                    if database.code().as_deref() == Some(#codes) =>
+                {
+                    #errors
+                }
+            )*
+            error => <#error as ::std::convert::From<::sqlx::Error>>::from(
+                error,
+            ),
+        }
+    };
+
+    if return_type.is_stream() {
+        stmts.push(Stmt::Expr(
+            parse_quote! {
+                ::futures::StreamExt::boxed(::futures::TryStreamExt::map_err(
+                    #last,
+                    #map_err,
+                ))
+            },
+            None,
+        ));
+    } else if let (Some(rows), Some(rows_error)) =
+        (sql_fn.expect_rows, &db.rows_error)
+    {
+        stmts.push(Stmt::Expr(last, None));
+
+        let block = &function.block;
+        let query_result = backend.query_result();
+        let value: Expr = match return_type {
+            ReturnType::RowsAffected => parse_quote! { Ok(rows) },
+            ReturnType::QueryResult => parse_quote! { Ok(result) },
+            _ => parse_quote! { Ok(()) },
+        };
+
+        function.block = parse_quote! {{
+            let result: ::sqlx::Result<#query_result> =
+                async move #block.await;
+            let result = result.map_err(#map_err)?;
+            let rows = result.rows_affected();
+
+            if rows != #rows {
+                return Err(::std::convert::From::from(
+                    #rows_error::Unexpected {
+                        expected: #rows,
+                        actual: rows,
+                    },
+                ));
+            }
+
+            #value
+        }};
+    } else {
+        stmts.push(Stmt::Expr(last, None));
+
+        let block = &function.block;
+        let result = return_type.as_type(db, lifetime, None);
+
+        function.block = parse_quote! {{
+            let result: #result = async move #block.await;
+            result.map_err(#map_err)
+        }};
+    }
+
+    parse_quote! { #function }
+}
+
+fn push_fns(
+    imp: &mut ItemImpl,
+    db: &Database,
+    skip: Target,
+    receiver: &Receiver,
+    executor: &Expr,
+) {
+    let elided: Lifetime = parse_quote! { '_ };
+    let named: Lifetime = parse_quote! { 's };
+
+    let mut bound = receiver.clone();
+    if let Some((_, lifetime)) = &mut bound.reference {
+        *lifetime = Some(named.clone());
+    }
+
+    for function in &db.functions {
+        if function.target == skip {
+            continue;
+        }
+
+        let return_type = function.return_type();
+
+        let item = if return_type.is_stream() && !return_type.is_owned_stream()
+        {
+            let mut item = make_fn(function, db, &bound, executor, &named);
+
+            item.sig.generics.params.insert(0, parse_quote! { #named });
+            bind_lifetimes(&mut item.sig, &named);
+
+            item
+        } else {
+            make_fn(function, db, receiver, executor, &elided)
+        };
+
+        imp.items.push(ImplItem::Fn(item));
+    }
+}
+
+fn expand_records(db: &Database, vis: &Visibility) -> TokenStream2 {
+    let derive = db.derive.iter();
+    let records = db.functions.iter().flat_map(|function| &function.records);
+
+    records
+        .map(|record| {
+            let Record {
+                attrs,
+                ident,
+                fields,
+            } = record;
+            let mut fields = fields.clone();
+
+            for field in fields.named.iter_mut() {
+                if let Visibility::Inherited = field.vis {
+                    field.vis = vis.clone();
+                }
+            }
+
+            let derive = derive.clone();
+
+            quote! {
+                #[derive(::sqlx::FromRow #(, #derive)*)]
+                #(#attrs)*
+                #vis struct #ident #fields
+            }
+        })
+        .collect()
+}
+
+fn expand_pagination(db: &Database, vis: &Visibility) -> TokenStream2 {
+    if db.pagination.is_none() {
+        return TokenStream2::new();
+    }
+
+    let (ident, request) = db.page_names();
+
+    quote! {
+        #[derive(Clone, Debug)]
+        #vis struct #ident<T, C = i64> {
+            #vis items: ::std::vec::Vec<T>,
+            #vis next: ::std::option::Option<C>,
+            #vis total: ::std::option::Option<i64>,
+        }
+
+        #[derive(Clone, Copy, Debug)]
+        #vis struct #request<C = i64> {
+            #vis limit: i64,
+            #vis cursor: ::std::option::Option<C>,
+        }
+
+        impl<C> #request<C> {
+            #vis fn new(limit: i64) -> Self {
+                Self {
+                    limit,
+                    cursor: None,
+                }
+            }
+
+            #vis fn cursor(mut self, cursor: C) -> Self {
+                self.cursor = Some(cursor);
+                self
+            }
+        }
+
+        impl<T, C: Clone> #ident<T, C> {
+            #vis fn next_request(
+                &self,
+                limit: i64,
+            ) -> ::std::option::Option<#request<C>> {
+                self.next.clone().map(|cursor| #request {
+                    limit,
+                    cursor: Some(cursor),
+                })
+            }
+        }
+    }
+}
+
+fn expand_exceptions(db: &Database, vis: &Visibility) -> TokenStream2 {
+    let Some(Exceptions {
+        ident, variants, ..
+    }) = &db.exceptions
+    else {
+        return TokenStream2::new();
+    };
+
+    let attrs = variants.iter().map(|variant| &variant.attrs);
+    let names: Vec<_> = variants.iter().map(|variant| &variant.ident).collect();
+    let codes = variants.iter().map(|variant| &variant.code);
+
+    quote! {
+        #[derive(Debug)]
+        #vis enum #ident {
+            #(
+                #(#attrs)*
+                #names {
+                    message: ::std::string::String,
+                    detail: ::std::option::Option<::std::string::String>,
+                    hint: ::std::option::Option<::std::string::String>,
+                },
+            )*
+            Sqlx(::sqlx::Error),
+        }
+
+        impl ::std::fmt::Display for #ident {
+            fn fmt(
+                &self,
+                f: &mut ::std::fmt::Formatter<'_>,
+            ) -> ::std::fmt::Result {
+                match self {
+                    #(Self::#names { message, .. } => f.write_str(message),)*
+                    Self::Sqlx(error) => ::std::fmt::Display::fmt(error, f),
+                }
+            }
+        }
+
+        impl ::std::error::Error for #ident {
+            fn source(
+                &self,
+            ) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)>
+            {
+                match self {
+                    Self::Sqlx(error) => Some(error),
+                    _ => None,
+                }
+            }
+        }
+
+        impl ::std::convert::From<::sqlx::Error> for #ident {
+            fn from(error: ::sqlx::Error) -> Self {
+                let ::sqlx::Error::Database(database) = &error else {
+                    return Self::Sqlx(error);
+                };
+
+                let Some(database) = database
+                    .try_downcast_ref::<::sqlx::postgres::PgDatabaseError>()
+                else {
+                    return Self::Sqlx(error);
+                };
+
+                let message = database.message().to_owned();
+                let detail =
+                    database.detail().map(::std::borrow::ToOwned::to_owned);
+                let hint =
+                    database.hint().map(::std::borrow::ToOwned::to_owned);
+
+                match database.code() {
+                    #(
+                        #codes => Self::#names {
+                            message,
+                            detail,
+                            hint,
+                        },
+                    )*
+                    _ => Self::Sqlx(error),
+                }
+            }
+        }
+    }
+}
+
+fn expand_rows_error(db: &Database, vis: &Visibility) -> TokenStream2 {
+    let Some(ident) = &db.rows_error else {
+        return TokenStream2::new();
+    };
+
+    quote! {
+        /// The error returned by `#[expect_rows]` functions: a row count
+        /// mismatch is `Unexpected`, and any other failure is `Sqlx`.
+        #[derive(Debug)]
+        #vis enum #ident {
+            Unexpected { expected: u64, actual: u64 },
+            Sqlx(::sqlx::Error),
+        }
+
+        impl ::std::fmt::Display for #ident {
+            fn fmt(
+                &self,
+                f: &mut ::std::fmt::Formatter<'_>,
+            ) -> ::std::fmt::Result {
+                match self {
+                    Self::Unexpected { expected, actual } => write!(
+                        f,
+                        "expected {expected} rows affected, got {actual}",
+                    ),
+                    Self::Sqlx(error) => ::std::fmt::Display::fmt(error, f),
+                }
+            }
+        }
+
+        impl ::std::error::Error for #ident {
+            fn source(
+                &self,
+            ) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)>
+            {
+                match self {
+                    Self::Sqlx(error) => Some(error),
+                    _ => None,
+                }
+            }
+        }
+
+        impl ::std::convert::From<::sqlx::Error> for #ident {
+            fn from(error: ::sqlx::Error) -> Self {
+                Self::Sqlx(error)
+            }
+        }
+    }
+}
+
+fn expand_cursor(db: &Database, vis: &Visibility) -> TokenStream2 {
+    if db.cursor.is_none() {
+        return TokenStream2::new();
+    }
+
+    let ident = db.cursor_name();
+
+    quote! {
+        #vis struct #ident<'c, T> {
+            conn: &'c mut ::sqlx::postgres::PgConnection,
+            name: ::std::string::String,
+            decode: fn(&::sqlx::postgres::PgRow) -> ::sqlx::Result<T>,
+        }
+
+        impl<T> #ident<'_, T> {
+            #vis async fn fetch_next(
+                &mut self,
+                count: usize,
+            ) -> ::sqlx::Result<::std::vec::Vec<T>> {
+                let sql = format!("FETCH FORWARD {} FROM {}", count, self.name);
+
+                ::sqlx::query(&sql)
+                    .persistent(false)
+                    .fetch_all(&mut *self.conn)
+                    .await?
+                    .iter()
+                    .map(self.decode)
+                    .collect()
+            }
+
+            #vis async fn move_to(
+                &mut self,
+                position: i64,
+            ) -> ::sqlx::Result<()> {
+                let sql =
+                    format!("MOVE ABSOLUTE {} IN {}", position, self.name);
+
+                ::sqlx::query(&sql)
+                    .persistent(false)
+                    .execute(&mut *self.conn)
+                    .await?;
+
+                Ok(())
+            }
+
+            #vis async fn close(self) -> ::sqlx::Result<()> {
+                let sql = format!("CLOSE {}", self.name);
+
+                ::sqlx::query(&sql)
+                    .persistent(false)
+                    .execute(&mut *self.conn)
+                    .await?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn expand_database(db: &Database, decl: &StructDecl) -> TokenStream2 {
+    let backend = db.backend;
+    let pool = backend.pool();
+    let StructDecl { vis, ident } = decl;
+
+    let item: Item = Item::Struct(parse_quote! {
+        #vis struct #ident {
+            pool: #pool,
+        }
+    });
+
+    let mut imp: ItemImpl = parse_quote! {
+        impl #ident {
+            pub fn new(pool: #pool) -> Self {
+                Self { pool }
+            }
+
+            pub async fn close(&self) {
+                self.pool.close().await
+            }
+        }
+    };
+
+    push_fns(
+        &mut imp,
+        db,
+        Target::Transaction,
+        &parse_quote! { &self },
+        &parse_quote! { &self.pool },
+    );
+
+    let fns = db.executor.as_ref().map(|name| {
+        let name = name
+            .clone()
+            .unwrap_or_else(|| format_ident!("{}Fns", ident));
+        let database = backend.database();
+
+        let item = make_trait(db, vis, &name);
+        let imp: ItemImpl = parse_quote! {
+            impl<'e, E> #name<'e> for E
+            where
+                E: ::sqlx::Executor<'e, Database = #database>,
+            {
+            }
+        };
+
+        quote! {
+            #item
+            #imp
+        }
+    });
+
+    quote! {
+        #item
+        #imp
+        #fns
+    }
+}
+
+fn expand_transaction(
+    tx: &Database,
+    decl: &StructDecl,
+    db: &Path,
+) -> TokenStream2 {
+    let backend = tx.backend;
+    let database = backend.database();
+    let StructDecl { vis, ident } = decl;
+
+    let item: Item = Item::Struct(parse_quote! {
+        #vis struct #ident {
+            inner: ::sqlx::Transaction<'static, #database>,
+        }
+    });
+
+    let begin: ItemImpl = parse_quote! {
+        impl #db {
+            #vis async fn begin(&self) -> ::sqlx::Result<#ident> {
+                Ok(#ident { inner: self.pool.begin().await? })
+            }
+
+            #vis async fn transaction<F, T, E>(
+                &self,
+                f: F,
+            ) -> ::std::result::Result<T, E>
+            where
+                F: for<'c> FnOnce(
+                    &'c mut #ident,
+                ) -> ::futures::future::BoxFuture<
+                    'c,
+                    ::std::result::Result<T, E>,
+                >,
+                E: From<::sqlx::Error>,
+            {
+                let mut tx = self.begin().await?;
+
+                match f(&mut tx).await {
+                    Ok(value) => {
+                        tx.commit().await?;
+                        Ok(value)
+                    }
+                    Err(error) => {
+                        let _ = tx.rollback().await;
+                        Err(error)
+                    }
+                }
+            }
+
+            /// Runs `f` in a transaction, starting over while fewer than
+            /// `attempts` tries have been made and `retry` returns `true`
+            /// for the error. Pass `Self::is_serialization_failure` when
+            /// `E` is `sqlx::Error`, or call it on the `sqlx::Error` that
+            /// `E` wraps.
+            #vis async fn transaction_with_retry<F, R, T, E>(
+                &self,
+                attempts: usize,
+                mut retry: R,
+                mut f: F,
+            ) -> ::std::result::Result<T, E>
+            where
+                F: for<'c> FnMut(
+                    &'c mut #ident,
+                ) -> ::futures::future::BoxFuture<
+                    'c,
+                    ::std::result::Result<T, E>,
+                >,
+                R: FnMut(&E) -> bool,
+                E: ::std::convert::From<::sqlx::Error>,
+            {
+                let mut attempt = 1;
+
+                loop {
+                    match self.transaction(&mut f).await {
+                        Err(error) if attempt < attempts && retry(&error) => {
+                            attempt += 1;
+                        }
+                        result => return result,
+                    }
+                }
+            }
+
+            /// Returns `true` for serialization failures and deadlocks,
+            /// which are safe to retry.
+            #vis fn is_serialization_failure(error: &::sqlx::Error) -> bool {
+                match error {
+                    ::sqlx::Error::Database(error) => matches!(
+                        error.code().as_deref(),
+                        Some("40001" | "40P01")
+                    ),
+                    _ => false,
+                }
+            }
+        }
+    };
+
+    let mut imp: ItemImpl = parse_quote! {
+        impl #ident {
+            pub async fn commit(self) -> ::sqlx::Result<()> {
+                self.inner.commit().await
+            }
+
+            pub async fn rollback(self) -> ::sqlx::Result<()> {
+                self.inner.rollback().await
+            }
+        }
+    };
+
+    let receiver: Receiver = parse_quote! { &mut self };
+    let executor: Expr = parse_quote! { &mut *self.inner };
+
+    push_fns(&mut imp, tx, Target::Pool, &receiver, &executor);
+
+    let options = tx.options.as_ref().map(|name| {
+        let name = name.clone().unwrap_or_else(|| {
+            Ident::new("TransactionOptions", Span::call_site())
+        });
+        let prefix = name.to_string();
+        let isolation_level = format_ident!(
+            "{}IsolationLevel",
+            prefix.strip_suffix("Options").unwrap_or(&prefix)
+        );
+
+        let levels = [
+            ("read_uncommitted", "ReadUncommitted", "READ UNCOMMITTED"),
+            ("read_committed", "ReadCommitted", "READ COMMITTED"),
+            ("repeatable_read", "RepeatableRead", "REPEATABLE READ"),
+            ("serializable", "Serializable", "SERIALIZABLE"),
+        ]
+        .map(|(method, variant, level)| {
+            (
+                Ident::new(method, Span::call_site()),
+                Ident::new(variant, Span::call_site()),
+                level,
+            )
+        });
+
+        let variants = levels.iter().map(|(_, variant, _)| variant);
+        let arms = levels.iter().map(|(_, variant, level)| {
+            quote! { Self::#variant => ::std::option::Option::Some(#level), }
+        });
+
+        let isolation: Item = Item::Enum(parse_quote! {
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+            #vis enum #isolation_level {
+                #[default]
+                Default,
+                #(#variants,)*
+            }
+        });
+
+        let isolation_imp: ItemImpl = parse_quote! {
+            impl #isolation_level {
+                fn as_sql(self) -> ::std::option::Option<&'static str> {
+                    match self {
+                        Self::Default => ::std::option::Option::None,
+                        #(#arms)*
+                    }
+                }
+            }
+        };
+
+        let item: Item = Item::Struct(parse_quote! {
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+            #vis struct #name {
+                pub isolation: #isolation_level,
+                pub read_only: bool,
+                pub deferrable: bool,
+            }
+        });
+
+        let levels = levels.iter().map(|(method, variant, _)| {
+            quote! {
+                pub fn #method(self) -> Self {
+                    Self {
+                        isolation: #isolation_level::#variant,
+                        ..self
+                    }
+                }
+            }
+        });
+
+        let options_imp: ItemImpl = parse_quote! {
+            impl #name {
+                pub fn new() -> Self {
+                    Self::default()
+                }
+
+                #(#levels)*
+
+                pub fn read_only(self) -> Self {
+                    Self {
+                        read_only: true,
+                        ..self
+                    }
+                }
+
+                pub fn deferrable(self) -> Self {
+                    Self {
+                        deferrable: true,
+                        ..self
+                    }
+                }
+
+                fn statement(
+                    &self,
+                ) -> ::std::option::Option<::std::string::String> {
+                    let mut modes = ::std::vec::Vec::new();
+
+                    if let Some(level) = self.isolation.as_sql() {
+                        modes.push(format!("ISOLATION LEVEL {level}"));
+                    }
+
+                    if self.read_only {
+                        modes.push("READ ONLY".to_owned());
+                    }
+
+                    if self.deferrable {
+                        modes.push("DEFERRABLE".to_owned());
+                    }
+
+                    if modes.is_empty() {
+                        None
+                    } else {
+                        Some(format!("SET TRANSACTION {}", modes.join(", ")))
+                    }
+                }
+            }
+        };
+
+        let begin_with: ItemImpl = parse_quote! {
+            impl #db {
+                #vis async fn begin_with(
+                    &self,
+                    options: #name,
+                ) -> ::sqlx::Result<#ident> {
+                    let mut inner = self.pool.begin().await?;
+
+                    if let Some(statement) = options.statement() {
+                        ::sqlx::Executor::execute(
+                            &mut *inner,
+                            statement.as_str(),
+                        )
+                        .await?;
+                    }
+
+                    Ok(#ident { inner })
+                }
+            }
+        };
+
+        quote! {
+            #isolation
+            #isolation_imp
+            #item
+            #options_imp
+            #begin_with
+        }
+    });
+
+    let prepared = tx.prepared.as_ref().map(|name| {
+        let name = name.clone().unwrap_or_else(|| {
+            Ident::new("PreparedTransaction", Span::call_site())
+        });
+
+        let item: Item = Item::Struct(parse_quote! {
+            #[derive(Clone, Debug)]
+            #vis struct #name {
+                gid: ::std::string::String,
+            }
+        });
+
+        let prepared_imp: ItemImpl = parse_quote! {
+            impl #name {
+                pub fn gid(&self) -> &str {
+                    &self.gid
+                }
+
+                fn statement(
+                    command: &str,
+                    gid: &str,
+                ) -> ::std::string::String {
+                    format!("{command} '{}'", gid.replace('\'', "''"))
+                }
+            }
+        };
+
+        imp.items.push(parse_quote! {
+            pub async fn prepare(
+                mut self,
+                gid: impl ::std::convert::Into<::std::string::String>,
+            ) -> ::sqlx::Result<#name> {
+                let gid = gid.into();
+                let statement = #name::statement("PREPARE TRANSACTION", &gid);
+
+                ::sqlx::Executor::execute(&mut *self.inner, statement.as_str())
+                    .await?;
+                self.inner.commit().await?;
+
+                Ok(#name { gid })
+            }
+        });
+
+        let db_imp: ItemImpl = parse_quote! {
+            impl #db {
+                #vis async fn commit_prepared(
+                    &self,
+                    gid: &str,
+                ) -> ::sqlx::Result<()> {
+                    let statement = #name::statement("COMMIT PREPARED", gid);
+
+                    ::sqlx::Executor::execute(&self.pool, statement.as_str())
+                        .await?;
+
+                    Ok(())
+                }
+
+                #vis async fn rollback_prepared(
+                    &self,
+                    gid: &str,
+                ) -> ::sqlx::Result<()> {
+                    let statement = #name::statement("ROLLBACK PREPARED", gid);
+
+                    ::sqlx::Executor::execute(&self.pool, statement.as_str())
+                        .await?;
+
+                    Ok(())
+                }
+
+                #vis async fn list_prepared(
+                    &self,
+                ) -> ::sqlx::Result<::std::vec::Vec<#name>> {
+                    let gids: ::std::vec::Vec<::std::string::String> =
+                        ::sqlx::query_scalar(
+                            "SELECT gid FROM pg_prepared_xacts \
+                            WHERE database = current_database()",
+                        )
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                    Ok(gids.into_iter().map(|gid| #name { gid }).collect())
+                }
+            }
+        };
+
+        quote! {
+            #item
+            #prepared_imp
+            #db_imp
+        }
+    });
+
+    let savepoint = tx.savepoint.as_ref().map(|name| {
+        let name = name
+            .clone()
+            .unwrap_or_else(|| Ident::new("Savepoint", Span::call_site()));
+
+        let item: Item = Item::Struct(parse_quote! {
+            #vis struct #name<'t> {
+                inner: ::sqlx::Transaction<'t, #database>,
+            }
+        });
+
+        let method: ImplItemFn = parse_quote! {
+            pub async fn savepoint(&mut self) -> ::sqlx::Result<#name<'_>> {
+                Ok(#name {
+                    inner: ::sqlx::Connection::begin(&mut *self.inner).await?,
+                })
+            }
+        };
+
+        imp.items.push(ImplItem::Fn(method.clone()));
+
+        let mut savepoint_imp: ItemImpl = parse_quote! {
+            impl<'t> #name<'t> {
+                #method
+
+                pub async fn commit(self) -> ::sqlx::Result<()> {
+                    self.inner.commit().await
+                }
+
+                pub async fn rollback(self) -> ::sqlx::Result<()> {
+                    self.inner.rollback().await
+                }
+            }
+        };
+
+        push_fns(&mut savepoint_imp, tx, Target::Pool, &receiver, &executor);
+
+        quote! {
+            #item
+            #savepoint_imp
+        }
+    });
+
+    quote! {
+        #item
+        #begin
+        #imp
+        #options
+        #prepared
+        #savepoint
+    }
+}
+
+fn check_names(db: &Database, database: bool) -> Result<()> {
+    let mut on_database: Vec<&str> = Vec::new();
+    let mut on_transaction: Vec<&str> = Vec::new();
+    let mut on_executor: Vec<&str> = Vec::new();
+
+    if database {
+        on_database.extend(["new", "close"]);
+
+        if db.transaction.is_some() {
+            on_database.extend([
+                "begin",
+                "transaction",
+                "transaction_with_retry",
+                "is_serialization_failure",
+            ]);
+        }
+
+        if db.options.is_some() {
+            on_database.push("begin_with");
+        }
+
+        if db.prepared.is_some() {
+            on_database.extend([
+                "commit_prepared",
+                "rollback_prepared",
+                "list_prepared",
+            ]);
+        }
+
+        if db.executor.is_some() {
+            on_executor.extend([
+                "execute",
+                "execute_many",
+                "fetch",
+                "fetch_many",
+                "fetch_all",
+                "fetch_one",
+                "fetch_optional",
+                "prepare",
+                "prepare_with",
+                "describe",
+            ]);
+        }
+    }
+
+    if !database || db.transaction.is_some() {
+        on_transaction.extend(["commit", "rollback"]);
+
+        if db.prepared.is_some() {
+            on_transaction.push("prepare");
+        }
+
+        if db.savepoint.is_some() {
+            on_transaction.push("savepoint");
+        }
+    }
+
+    for (i, function) in db.functions.iter().enumerate() {
+        let name = function.name.unraw();
+
+        if db.functions[..i]
+            .iter()
+            .any(|previous| previous.name.unraw() == name)
+        {
+            return Err(syn::Error::new(
+                function.name.span(),
+                format!("duplicate function `{name}`"),
+            ));
+        }
+
+        let in_trait = function.in_trait();
+
+        let conflict = (function.target != Target::Transaction
+            && on_database.iter().any(|generated| name == generated))
+            || (function.target != Target::Pool
+                && on_transaction.iter().any(|generated| name == generated))
+            || (in_trait
+                && on_executor.iter().any(|generated| name == generated));
+
+        if conflict {
+            return Err(syn::Error::new(
+                function.name.span(),
+                format!("`{name}` conflicts with a generated method"),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[proc_macro]
+pub fn database(input: TokenStream) -> TokenStream {
+    let mut db = parse_macro_input!(input as Database);
+
+    if let Some(database) = &db.database {
+        return syn::Error::new_spanned(
+            database,
+            "`database` is only valid in `transaction!`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    if db.transaction.is_none() {
+        if let Some(function) = db
+            .functions
+            .iter()
+            .find(|function| function.return_type().is_cursor())
+        {
+            return syn::Error::new_spanned(
+                &function.output,
+                "cursors are only available on transactions",
+            )
+            .to_compile_error()
+            .into();
+        }
+
+        for (enabled, key) in [
+            (db.options.is_some(), "options"),
+            (db.prepared.is_some(), "prepared"),
+            (db.savepoint.is_some(), "savepoint"),
+            (db.cursor.is_some(), "cursor"),
+        ] {
+            if enabled {
+                return syn::Error::new(
+                    Span::call_site(),
+                    format!("`{key}` requires `transaction`"),
+                )
+                .to_compile_error()
+                .into();
+            }
+        }
+    }
+
+    if db.transaction.is_none() {
+        if let Some(attr) = db
+            .functions
+            .iter()
+            .filter(|function| function.target == Target::Transaction)
+            .find_map(|function| function.target_attr.as_ref())
+        {
+            return syn::Error::new(
+                attr.span(),
+                format!("`{attr}` requires `transaction`"),
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    if let Err(error) = check_names(&db, true) {
+        return error.to_compile_error().into();
+    }
+
+    let decl = StructDecl::or_default(db.decl.take(), "Database");
+    let mut output = expand_database(&db, &decl);
+    output.extend(expand_records(&db, &decl.vis));
+    output.extend(expand_pagination(&db, &decl.vis));
+    output.extend(expand_cursor(&db, &decl.vis));
+    output.extend(expand_exceptions(&db, &decl.vis));
+    output.extend(expand_rows_error(&db, &decl.vis));
+
+    if let Some(name) = &db.transaction {
+        let tx = StructDecl {
+            vis: decl.vis.clone(),
+            ident: name.clone().unwrap_or_else(|| {
+                Ident::new("Transaction", Span::call_site())
+            }),
+        };
+        let path: Path = decl.ident.clone().into();
+
+        output.extend(expand_transaction(&db, &tx, &path));
+    }
+
+    output.into()
+}
+
+#[proc_macro]
+pub fn transaction(input: TokenStream) -> TokenStream {
+    let mut tx = parse_macro_input!(input as Database);
+
+    for (option, key) in
+        [(&tx.executor, "executor"), (&tx.transaction, "transaction")]
+    {
+        if option.is_some() {
+            return syn::Error::new(
+                Span::call_site(),
+                format!("`{key}` is only valid in `database!`"),
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    if let Some(function) = tx
+        .functions
+        .iter()
+        .find(|function| function.return_type().is_owned_stream())
+    {
+        return syn::Error::new_spanned(
+            &function.output,
+            "`OwnedStream` is only available on the database",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    if let Some(attr) = tx
+        .functions
+        .iter()
+        .filter(|function| function.target == Target::Pool)
+        .find_map(|function| function.target_attr.as_ref())
+    {
+        return syn::Error::new(
+            attr.span(),
+            format!("`{attr}` is only valid in `database!`"),
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    if let Err(error) = check_names(&tx, false) {
+        return error.to_compile_error().into();
+    }
+
+    let decl = StructDecl::or_default(tx.decl.take(), "Transaction");
+    let db: Path = tx
+        .database
+        .clone()
+        .unwrap_or_else(|| parse_quote! { Database });
+
+    let mut output = expand_transaction(&tx, &decl, &db);
+    output.extend(expand_records(&tx, &decl.vis));
+    output.extend(expand_pagination(&tx, &decl.vis));
+    output.extend(expand_cursor(&tx, &decl.vis));
+    output.extend(expand_exceptions(&tx, &decl.vis));
+    output.extend(expand_rows_error(&tx, &decl.vis));
 
     output.into()
 }
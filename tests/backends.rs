@@ -0,0 +1,114 @@
+#![allow(dead_code)]
+
+use std::future::Future;
+
+fn returns<T, E>(_: impl Future<Output = Result<T, E>>) {}
+
+#[cfg(feature = "mysql")]
+mod mysql {
+    use super::*;
+    use sqlx::mysql::MySqlQueryResult;
+
+    sqlx_helper_macros::database! {
+        backend = mysql;
+        rows_error;
+        schema = "Billing";
+        purge(days: i32) -> u64;
+        purge_result(days: i32) -> QueryResult;
+        #[expect_rows(1)]
+        delete_user(id: i64);
+        #[expect_rows(1)]
+        delete_users(id: i64) -> u64;
+        procedure archive(id: i64);
+    }
+
+    sqlx_helper_macros::transaction! {
+        backend = mysql;
+        rows_error = TransactionRowsError;
+        #[expect_rows(1)]
+        delete_user(id: i64);
+    }
+
+    fn _signatures(db: &Database, tx: &mut Transaction) {
+        returns::<u64, sqlx::Error>(db.purge(1));
+        returns::<MySqlQueryResult, sqlx::Error>(db.purge_result(1));
+        returns::<(), RowsError>(db.delete_user(1));
+        returns::<u64, RowsError>(db.delete_users(1));
+        returns::<(), TransactionRowsError>(tx.delete_user(1));
+    }
+
+    #[test]
+    fn unexpected_rows_are_distinct() {
+        let error = RowsError::Unexpected {
+            expected: 1,
+            actual: 0,
+        };
+
+        assert_eq!(error.to_string(), "expected 1 rows affected, got 0");
+        assert!(std::error::Error::source(&error).is_none());
+        assert!(matches!(
+            RowsError::from(sqlx::Error::RowNotFound),
+            RowsError::Sqlx(sqlx::Error::RowNotFound)
+        ));
+    }
+}
+
+#[cfg(feature = "mysql")]
+mod mysql_error {
+    use super::*;
+
+    #[derive(Debug)]
+    pub enum AppError {
+        Sqlx(sqlx::Error),
+        Rows { expected: u64, actual: u64 },
+    }
+
+    impl From<sqlx::Error> for AppError {
+        fn from(error: sqlx::Error) -> Self {
+            Self::Sqlx(error)
+        }
+    }
+
+    impl From<RowsError> for AppError {
+        fn from(error: RowsError) -> Self {
+            match error {
+                RowsError::Unexpected { expected, actual } => {
+                    Self::Rows { expected, actual }
+                }
+                RowsError::Sqlx(error) => Self::Sqlx(error),
+            }
+        }
+    }
+
+    sqlx_helper_macros::database! {
+        backend = mysql;
+        error = AppError;
+        rows_error;
+        #[expect_rows(1)]
+        delete_user(id: i64) -> u64;
+    }
+
+    fn _signatures(db: &Database) {
+        returns::<u64, AppError>(db.delete_user(1));
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        transaction;
+        executor;
+        rows_error;
+        users() -> Vec<(i64, String)>;
+        #[expect_rows(1)]
+        delete_user(id: i64);
+    }
+
+    fn _signatures(db: &Database, tx: &mut Transaction) {
+        returns::<Vec<(i64, String)>, sqlx::Error>(db.users());
+        returns::<(), RowsError>(tx.delete_user(1));
+    }
+}
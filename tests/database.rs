@@ -0,0 +1,275 @@
+#![allow(dead_code)]
+
+use futures::stream::BoxStream;
+use sqlx::FromRow;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+
+#[derive(Debug, FromRow)]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+}
+
+mod functions {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        create_user(name: &str) -> i64;
+        read_user(id: i64) -> Option<User>;
+        users() -> Vec<User>;
+        user_names() -> Vec<String>;
+        nickname(id: i64) -> Option<Option<String>>;
+        delete_user(id: i64);
+        pair(id: i64) -> (i64, String);
+        #[scalar]
+        raw(id: i64) -> Vec<u8>;
+        #[row]
+        profile(id: i64) -> User;
+        procedure archive(cutoff: i64);
+        search(
+            name: &str,
+            #[sql(default)] active: Option<bool>,
+            #[sql(default, name = "max_age")] age: Option<i32>,
+        ) -> Vec<User>;
+        r#type() -> String;
+    }
+
+    fn _signatures(db: &Database) {
+        fn returns<T>(_: impl Future<Output = sqlx::Result<T>>) {}
+
+        returns::<i64>(db.create_user("name"));
+        returns::<Option<User>>(db.read_user(1));
+        returns::<Vec<User>>(db.users());
+        returns::<Vec<String>>(db.user_names());
+        returns::<Option<Option<String>>>(db.nickname(1));
+        returns::<()>(db.delete_user(1));
+        returns::<(i64, String)>(db.pair(1));
+        returns::<Vec<u8>>(db.raw(1));
+        returns::<User>(db.profile(1));
+        returns::<()>(db.archive(1));
+        returns::<Vec<User>>(db.search("name", None, Some(30)));
+        returns::<String>(db.r#type());
+    }
+}
+
+mod streams {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        users() -> Stream<User>;
+        ids(name: &str) -> Stream<i64>;
+        #[chunk_size(100)]
+        batches() -> Stream<Vec<User>>;
+        pages() -> Chunks<User, 50>;
+        export(name: String) -> OwnedStream<User>;
+    }
+
+    fn _signatures(db: &Database, name: &str) {
+        let _: BoxStream<'_, sqlx::Result<User>> = db.users();
+        let _: BoxStream<'_, sqlx::Result<i64>> = db.ids(name);
+        let _: BoxStream<'_, sqlx::Result<Vec<User>>> = db.batches();
+        let _: BoxStream<'_, sqlx::Result<Vec<User>>> = db.pages();
+        let _: BoxStream<'static, sqlx::Result<User>> =
+            db.export(name.to_owned());
+    }
+}
+
+mod maps {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        #[key = "id"]
+        by_id() -> HashMap<i64, User>;
+        #[key = "name"]
+        by_name() -> BTreeMap<String, Vec<User>>;
+        names() -> HashMap<i64, String>;
+    }
+
+    fn _signatures(db: &Database) {
+        fn returns<T>(_: impl Future<Output = sqlx::Result<T>>) {}
+
+        returns::<HashMap<i64, User>>(db.by_id());
+        returns::<BTreeMap<String, Vec<User>>>(db.by_name());
+        returns::<HashMap<i64, String>>(db.names());
+    }
+}
+
+mod procedures {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        procedure purge(days: i32);
+    }
+
+    fn _signatures(db: &Database) {
+        fn returns<T>(_: impl Future<Output = sqlx::Result<T>>) {}
+
+        returns::<()>(db.purge(1));
+    }
+}
+
+mod records {
+    sqlx_helper_macros::database! {
+        derive = Clone, Debug;
+        profile(id: i64) -> record Profile { id: i64, bio: Option<String> };
+        profiles() -> Vec<#[derive(PartialEq)] record Summary { id: i64 }>;
+    }
+
+    #[test]
+    fn records_are_public_with_derives() {
+        let profile = Profile { id: 1, bio: None };
+        let summary = Summary { id: 1 };
+
+        assert_eq!(profile.clone().id, 1);
+        assert_eq!(summary.clone(), Summary { id: 1 });
+    }
+}
+
+mod pagination {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        pagination;
+        users(name: &str) -> Page<User>;
+        #[count = "count_users"]
+        counted() -> Page<User>;
+        #[key = "name"]
+        by_name() -> Page<User, String>;
+    }
+
+    sqlx_helper_macros::database! {
+        struct Analytics;
+        pagination = Report;
+        reports() -> Page<i64>;
+    }
+
+    fn _signatures(db: &Database, analytics: &Analytics) {
+        fn returns<T>(_: impl Future<Output = sqlx::Result<T>>) {}
+
+        returns::<Page<User>>(db.users("name", PageRequest::new(10)));
+        returns::<Page<User>>(db.counted(PageRequest::new(10)));
+        returns::<Page<User, String>>(db.by_name(PageRequest::new(10)));
+        returns::<Report<i64>>(analytics.reports(ReportRequest::new(10)));
+    }
+
+    #[test]
+    fn next_request_continues_from_cursor() {
+        let page: Page<i64, String> = Page {
+            items: vec![1, 2],
+            next: Some("b".to_owned()),
+            total: None,
+        };
+
+        let request = page.next_request(2).unwrap();
+
+        assert_eq!(request.limit, 2);
+        assert_eq!(request.cursor.as_deref(), Some("b"));
+        assert!(PageRequest::<i64>::new(5).cursor.is_none());
+        assert_eq!(PageRequest::new(5).cursor(10).cursor, Some(10));
+    }
+
+    #[test]
+    fn last_page_has_no_next_request() {
+        let page: Report<i64> = Report {
+            items: Vec::new(),
+            next: None,
+            total: Some(0),
+        };
+
+        assert!(page.next_request(10).is_none());
+    }
+}
+
+mod schema {
+    sqlx_helper_macros::database! {
+        schema = "Billing";
+        create_invoice(amount: i64) -> i64;
+        #[sql(name = "audit.log_event")]
+        log_event(message: &str);
+    }
+}
+
+mod executor {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        executor;
+        pagination;
+        create_user(name: &str) -> i64;
+        users() -> Stream<User>;
+        #[count = "count_users"]
+        counted() -> Page<User>;
+        export() -> OwnedStream<User>;
+    }
+
+    fn _signatures(db: &Database, conn: &mut sqlx::PgConnection) {
+        fn returns<T>(_: impl Future<Output = sqlx::Result<T>>) {}
+
+        returns::<i64>(conn.create_user("name"));
+        let _: BoxStream<'_, sqlx::Result<User>> = conn.users();
+        returns::<Page<User>>(db.counted(PageRequest::new(1)));
+    }
+}
+
+mod errors {
+    use super::*;
+
+    #[derive(Debug)]
+    pub enum AppError {
+        Conflict,
+        Sqlx(sqlx::Error),
+    }
+
+    impl From<sqlx::Error> for AppError {
+        fn from(error: sqlx::Error) -> Self {
+            Self::Sqlx(error)
+        }
+    }
+
+    sqlx_helper_macros::database! {
+        error = AppError;
+        #[on_error("23505" => AppError::Conflict)]
+        create_user(name: &str) -> i64;
+        users() -> Stream<User>;
+    }
+
+    fn _signatures(db: &Database) {
+        fn returns<T>(_: impl Future<Output = Result<T, AppError>>) {}
+
+        returns::<i64>(db.create_user("name"));
+        let _: BoxStream<'_, Result<User, AppError>> = db.users();
+    }
+}
+
+mod exceptions {
+    sqlx_helper_macros::database! {
+        exceptions {
+            InsufficientFunds = "P0001",
+            AccountLocked = "P0002",
+        }
+        withdraw(account: i64, amount: i64) -> i64;
+    }
+
+    #[test]
+    fn other_errors_are_wrapped() {
+        let error = DatabaseError::from(sqlx::Error::RowNotFound);
+
+        assert!(matches!(
+            error,
+            DatabaseError::Sqlx(sqlx::Error::RowNotFound)
+        ));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn exceptions_display_their_message() {
+        let error = DatabaseError::AccountLocked {
+            message: "account is locked".to_owned(),
+            detail: None,
+            hint: None,
+        };
+
+        assert_eq!(error.to_string(), "account is locked");
+    }
+}
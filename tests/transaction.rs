@@ -0,0 +1,177 @@
+#![allow(dead_code)]
+
+use futures::{future::BoxFuture, FutureExt};
+use sqlx::FromRow;
+use std::future::Future;
+
+#[derive(Debug, FromRow)]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+}
+
+fn returns<T, E>(_: impl Future<Output = Result<T, E>>) {}
+
+mod transaction {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        transaction;
+        options;
+        prepared;
+        savepoint;
+        cursor;
+        create_user(name: &str) -> i64;
+        #[pool_only]
+        refresh();
+        #[tx_only]
+        lock_users();
+        walk() -> Cursor<User>;
+        export() -> OwnedStream<User>;
+    }
+
+    fn _signatures(db: &Database, tx: &mut Transaction) {
+        returns::<i64, sqlx::Error>(db.create_user("name"));
+        returns::<(), sqlx::Error>(db.refresh());
+        returns::<i64, sqlx::Error>(tx.create_user("name"));
+        returns::<(), sqlx::Error>(tx.lock_users());
+        returns::<Cursor<'_, User>, sqlx::Error>(tx.walk());
+        returns::<Transaction, sqlx::Error>(
+            db.begin_with(TransactionOptions::new().serializable()),
+        );
+    }
+
+    async fn _closures(db: &Database) -> sqlx::Result<i64> {
+        let id = db
+            .transaction(|tx| async move { tx.create_user("a").await }.boxed())
+            .await?;
+
+        db.transaction_with_retry(3, Database::is_serialization_failure, |tx| {
+            async move { Ok(tx.create_user("b").await? + id) }.boxed()
+        })
+        .await
+    }
+
+    #[test]
+    fn options_build_set_transaction() {
+        let options = TransactionOptions {
+            isolation: TransactionIsolationLevel::Serializable,
+            read_only: true,
+            deferrable: true,
+        };
+
+        assert_eq!(
+            options.statement().as_deref(),
+            Some(
+                "SET TRANSACTION ISOLATION LEVEL SERIALIZABLE, \
+                READ ONLY, DEFERRABLE"
+            )
+        );
+        assert_eq!(
+            TransactionOptions::new()
+                .read_committed()
+                .statement()
+                .as_deref(),
+            Some("SET TRANSACTION ISOLATION LEVEL READ COMMITTED")
+        );
+        assert_eq!(TransactionOptions::default().statement(), None);
+    }
+
+    #[test]
+    fn prepared_ids_are_quoted() {
+        assert_eq!(
+            PreparedTransaction::statement("COMMIT PREPARED", "it's"),
+            "COMMIT PREPARED 'it''s'"
+        );
+    }
+
+    #[test]
+    fn only_database_errors_are_retried() {
+        assert!(!Database::is_serialization_failure(
+            &sqlx::Error::RowNotFound
+        ));
+    }
+}
+
+mod retry {
+    use super::*;
+
+    #[derive(Debug)]
+    pub enum AppError {
+        Sqlx(sqlx::Error),
+    }
+
+    impl From<sqlx::Error> for AppError {
+        fn from(error: sqlx::Error) -> Self {
+            Self::Sqlx(error)
+        }
+    }
+
+    sqlx_helper_macros::database! {
+        transaction;
+        create_user(name: &str) -> i64;
+    }
+
+    fn retry(error: &AppError) -> bool {
+        let AppError::Sqlx(error) = error;
+        Database::is_serialization_failure(error)
+    }
+
+    fn create(tx: &mut Transaction) -> BoxFuture<'_, Result<i64, AppError>> {
+        async move { Ok(tx.create_user("name").await?) }.boxed()
+    }
+
+    fn _signatures(db: &Database) {
+        returns::<i64, AppError>(db.transaction_with_retry(3, retry, create));
+    }
+}
+
+mod shared {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        transaction;
+        options;
+        cursor;
+        users() -> Vec<User>;
+        walk() -> Cursor<User>;
+    }
+
+    sqlx_helper_macros::database! {
+        struct Analytics;
+        transaction = AnalyticsTransaction;
+        options = AnalyticsOptions;
+        cursor = AnalyticsCursor;
+        walk() -> Cursor<i64>;
+    }
+
+    fn _signatures(tx: &mut AnalyticsTransaction) {
+        returns::<AnalyticsCursor<'_, i64>, sqlx::Error>(tx.walk());
+        let _ = AnalyticsOptions {
+            isolation: AnalyticsIsolationLevel::RepeatableRead,
+            ..Default::default()
+        };
+    }
+}
+
+mod standalone {
+    use super::*;
+
+    sqlx_helper_macros::database! {
+        pagination;
+        users() -> Page<User>;
+    }
+
+    sqlx_helper_macros::transaction! {
+        struct Tx;
+        pagination = TxPage;
+        users() -> Page<User>;
+        delete_user(id: i64);
+    }
+
+    fn _signatures(db: &Database, tx: &mut Tx) {
+        returns::<Page<User>, sqlx::Error>(db.users(PageRequest::new(1)));
+        returns::<TxPage<User>, sqlx::Error>(tx.users(TxPageRequest::new(1)));
+        returns::<(), sqlx::Error>(tx.delete_user(1));
+    }
+}
@@ -0,0 +1,8 @@
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+
+    #[cfg(feature = "sqlite")]
+    t.compile_fail("tests/ui/sqlite/*.rs");
+}
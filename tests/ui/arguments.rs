@@ -0,0 +1,54 @@
+mod pattern {
+    sqlx_helper_macros::database! {
+        create_point((x, y): (i64, i64));
+    }
+}
+
+mod name_without_default {
+    sqlx_helper_macros::database! {
+        create_user(#[sql(name = "user_name")] name: &str);
+    }
+}
+
+mod default_not_option {
+    sqlx_helper_macros::database! {
+        create_user(#[sql(default)] name: &str);
+    }
+}
+
+mod unknown_sql_attribute {
+    sqlx_helper_macros::database! {
+        create_user(#[sql(rename = "user_name")] name: &str);
+    }
+}
+
+mod checked_default {
+    sqlx_helper_macros::database! {
+        #[checked]
+        create_user(#[sql(default)] name: Option<String>);
+    }
+}
+
+mod too_many_defaults {
+    sqlx_helper_macros::database! {
+        search(
+            #[sql(default)] a: Option<i64>,
+            #[sql(default)] b: Option<i64>,
+            #[sql(default)] c: Option<i64>,
+            #[sql(default)] d: Option<i64>,
+            #[sql(default)] e: Option<i64>,
+            #[sql(default)] f: Option<i64>,
+            #[sql(default)] g: Option<i64>,
+            #[sql(default)] h: Option<i64>,
+            #[sql(default)] i: Option<i64>,
+        );
+    }
+}
+
+mod owned_stream_borrow {
+    sqlx_helper_macros::database! {
+        export(name: &str) -> OwnedStream<i64>;
+    }
+}
+
+fn main() {}
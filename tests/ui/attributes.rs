@@ -0,0 +1,92 @@
+mod scalar_and_row {
+    sqlx_helper_macros::database! {
+        #[scalar]
+        #[row]
+        user_id() -> i64;
+    }
+}
+
+mod pool_and_tx {
+    sqlx_helper_macros::database! {
+        transaction;
+        #[pool_only]
+        #[tx_only]
+        refresh();
+    }
+}
+
+mod unknown_sql_attribute {
+    sqlx_helper_macros::database! {
+        #[sql(schema = "billing")]
+        refresh();
+    }
+}
+
+mod zero_chunk_size {
+    sqlx_helper_macros::database! {
+        #[chunk_size(0)]
+        ids() -> Stream<Vec<i64>>;
+    }
+}
+
+mod chunk_size_without_stream {
+    sqlx_helper_macros::database! {
+        #[chunk_size(10)]
+        ids() -> Vec<i64>;
+    }
+}
+
+mod key_without_map {
+    sqlx_helper_macros::database! {
+        #[key = "id"]
+        ids() -> Vec<i64>;
+    }
+}
+
+mod key_not_string {
+    sqlx_helper_macros::database! {
+        #[key = 1]
+        ids() -> Vec<i64>;
+    }
+}
+
+mod count_without_page {
+    sqlx_helper_macros::database! {
+        #[count = "count_ids"]
+        ids() -> Vec<i64>;
+    }
+}
+
+mod count_with_default {
+    sqlx_helper_macros::database! {
+        pagination;
+        #[count = "count_ids"]
+        ids(#[sql(default)] active: Option<bool>) -> Page<i64>;
+    }
+}
+
+mod expect_rows_with_rows {
+    sqlx_helper_macros::database! {
+        #[expect_rows(1)]
+        ids() -> Vec<i64>;
+    }
+}
+
+mod on_error_without_error {
+    sqlx_helper_macros::database! {
+        #[on_error("23505" => ())]
+        refresh();
+    }
+}
+
+mod on_error_bad_sqlstate {
+    pub struct Error;
+
+    sqlx_helper_macros::database! {
+        error = Error;
+        #[on_error("235" => Error)]
+        refresh();
+    }
+}
+
+fn main() {}
@@ -0,0 +1,77 @@
+mod duplicate_function {
+    sqlx_helper_macros::database! {
+        refresh();
+        r#refresh();
+    }
+}
+
+mod database_method {
+    sqlx_helper_macros::database! {
+        close();
+    }
+}
+
+mod transaction_method {
+    sqlx_helper_macros::database! {
+        transaction;
+        #[tx_only]
+        commit();
+    }
+}
+
+mod database_transaction_method {
+    sqlx_helper_macros::database! {
+        transaction;
+        begin();
+    }
+}
+
+mod standalone_transaction_method {
+    sqlx_helper_macros::transaction! {
+        rollback();
+    }
+}
+
+mod executor_method {
+    sqlx_helper_macros::database! {
+        executor;
+        fetch_all();
+    }
+}
+
+mod reserved_query {
+    sqlx_helper_macros::database! {
+        search(query: &str);
+    }
+}
+
+mod reserved_variant {
+    sqlx_helper_macros::database! {
+        search(variant: i64, #[sql(default)] active: Option<bool>);
+    }
+}
+
+mod reserved_page {
+    sqlx_helper_macros::database! {
+        pagination;
+        ids(page: i64) -> Page<i64>;
+    }
+}
+
+mod reserved_total {
+    sqlx_helper_macros::database! {
+        pagination;
+        #[count = "count_ids"]
+        ids(total: i64) -> Page<i64>;
+    }
+}
+
+mod reserved_cursor {
+    sqlx_helper_macros::database! {
+        transaction;
+        cursor;
+        ids(cursor: i64) -> Cursor<i64>;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,125 @@
+mod duplicate_option {
+    sqlx_helper_macros::database! {
+        transaction;
+        transaction;
+    }
+}
+
+mod unknown_option {
+    sqlx_helper_macros::database! {
+        pool_size = 5;
+    }
+}
+
+mod unknown_backend {
+    sqlx_helper_macros::database! {
+        backend = oracle;
+    }
+}
+
+mod database_in_database {
+    sqlx_helper_macros::database! {
+        database = Database;
+    }
+}
+
+mod options_without_transaction {
+    sqlx_helper_macros::database! {
+        options;
+    }
+}
+
+mod tx_only_without_transaction {
+    sqlx_helper_macros::database! {
+        #[tx_only]
+        lock_users();
+    }
+}
+
+mod cursor_function_without_transaction {
+    sqlx_helper_macros::database! {
+        cursor;
+        ids() -> Cursor<i64>;
+    }
+}
+
+mod pool_only_cursor {
+    sqlx_helper_macros::database! {
+        transaction;
+        cursor;
+        #[pool_only]
+        ids() -> Cursor<i64>;
+    }
+}
+
+mod tx_only_owned_stream {
+    sqlx_helper_macros::database! {
+        transaction;
+        #[tx_only]
+        ids() -> OwnedStream<i64>;
+    }
+}
+
+mod executor_in_transaction {
+    sqlx_helper_macros::transaction! {
+        executor;
+    }
+}
+
+mod pool_only_in_transaction {
+    sqlx_helper_macros::transaction! {
+        #[pool_only]
+        refresh();
+    }
+}
+
+mod owned_stream_in_transaction {
+    sqlx_helper_macros::transaction! {
+        ids() -> OwnedStream<i64>;
+    }
+}
+
+mod rows_error_on_postgres {
+    sqlx_helper_macros::database! {
+        rows_error;
+    }
+}
+
+mod exceptions_with_error {
+    pub struct Error;
+
+    sqlx_helper_macros::database! {
+        error = Error;
+        exceptions {
+            Locked = "P0001",
+        }
+    }
+}
+
+mod reserved_exception {
+    sqlx_helper_macros::database! {
+        exceptions {
+            Sqlx = "P0001",
+        }
+    }
+}
+
+mod duplicate_exception {
+    sqlx_helper_macros::database! {
+        exceptions {
+            Locked = "P0001",
+            Locked = "P0002",
+        }
+    }
+}
+
+mod duplicate_sqlstate {
+    sqlx_helper_macros::database! {
+        exceptions {
+            Locked = "P0001",
+            Closed = "P0001",
+        }
+    }
+}
+
+fn main() {}
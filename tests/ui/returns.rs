@@ -0,0 +1,134 @@
+#[derive(sqlx::FromRow)]
+pub struct User {
+    pub id: i64,
+}
+
+mod wide_tuple {
+    sqlx_helper_macros::database! {
+        row() -> (
+            i64, i64, i64, i64, i64, i64, i64, i64,
+            i64, i64, i64, i64, i64, i64, i64, i64,
+            i64,
+        );
+    }
+}
+
+mod checked_tuple {
+    sqlx_helper_macros::database! {
+        #[checked]
+        row() -> (i64, String);
+    }
+}
+
+mod checked_map {
+    use super::User;
+    use std::collections::HashMap;
+
+    sqlx_helper_macros::database! {
+        #[checked]
+        #[key = "id"]
+        users() -> HashMap<i64, User>;
+    }
+}
+
+mod checked_page {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        pagination;
+        #[checked]
+        users() -> Page<User>;
+    }
+}
+
+mod checked_cursor {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        transaction;
+        cursor;
+        #[checked]
+        users() -> Cursor<User>;
+    }
+}
+
+mod checked_external_row {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        #[checked]
+        users() -> Vec<User>;
+    }
+}
+
+mod offset_cursor {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        pagination;
+        users() -> Page<User, String>;
+    }
+}
+
+mod bad_generics {
+    sqlx_helper_macros::database! {
+        ids() -> Option<i64, i64>;
+    }
+}
+
+mod bad_chunks {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        users() -> Chunks<User>;
+    }
+}
+
+mod map_without_key {
+    use super::User;
+    use std::collections::HashMap;
+
+    sqlx_helper_macros::database! {
+        users() -> HashMap<i64, User>;
+    }
+}
+
+mod stream_of_vec_without_chunk_size {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        users() -> Stream<Vec<User>>;
+    }
+}
+
+mod page_without_pagination {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        users() -> Page<User>;
+    }
+}
+
+mod cursor_without_cursor {
+    use super::User;
+
+    sqlx_helper_macros::database! {
+        transaction;
+        users() -> Cursor<User>;
+    }
+}
+
+mod rows_affected {
+    sqlx_helper_macros::database! {
+        procedure purge(days: i32) -> RowsAffected;
+    }
+}
+
+mod expect_rows {
+    sqlx_helper_macros::database! {
+        #[expect_rows(1)]
+        procedure purge(days: i32);
+    }
+}
+
+fn main() {}
@@ -0,0 +1,64 @@
+mod options {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        transaction;
+        options;
+    }
+}
+
+mod prepared {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        transaction;
+        prepared;
+    }
+}
+
+mod cursor {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        transaction;
+        cursor;
+    }
+}
+
+mod cursor_function {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        transaction;
+        ids() -> Cursor<i64>;
+    }
+}
+
+mod exceptions {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        exceptions {
+            Locked = "P0001",
+        }
+    }
+}
+
+mod procedure {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        procedure purge(days: i32);
+    }
+}
+
+mod default_argument {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        search(#[sql(default)] active: Option<bool>);
+    }
+}
+
+mod expect_rows_without_rows_error {
+    sqlx_helper_macros::database! {
+        backend = sqlite;
+        #[expect_rows(1)]
+        purge(days: i32);
+    }
+}
+
+fn main() {}